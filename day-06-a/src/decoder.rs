@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    Packet,
+    Message,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub kind: FrameKind,
+    pub marker_offset: usize,
+    pub payload_offset: usize,
+    pub payload: String,
+}
+
+// by default markers never overlap, so each one after the first is only looked for from where
+// the previous one's payload starts. with overlapping on, any later window counts, even one that
+// starts inside the previous marker
+#[derive(Debug, Clone, Copy)]
+pub struct Decoder {
+    packet_marker_len: usize,
+    message_marker_len: usize,
+    overlapping: bool,
+}
+
+impl Decoder {
+    pub fn new(packet_marker_len: usize, message_marker_len: usize) -> Result<Self, String> {
+        if packet_marker_len == 0 || message_marker_len == 0 {
+            Err(format!(
+                "marker lengths must be positive, packet = {}, message = {}",
+                packet_marker_len, message_marker_len
+            ))
+        } else {
+            Ok(Self {
+                packet_marker_len,
+                message_marker_len,
+                overlapping: false,
+            })
+        }
+    }
+
+    pub fn overlapping(self, overlapping: bool) -> Self {
+        Self {
+            overlapping,
+            ..self
+        }
+    }
+
+    pub fn packets(&self, s: &str) -> Frames {
+        Frames::new(
+            s,
+            FrameKind::Packet,
+            self.packet_marker_len,
+            self.overlapping,
+        )
+    }
+
+    pub fn messages(&self, s: &str) -> Frames {
+        Frames::new(
+            s,
+            FrameKind::Message,
+            self.message_marker_len,
+            self.overlapping,
+        )
+    }
+
+    // both channels interleaved in stream order, packets first when two frames share a marker offset
+    pub fn frames(&self, s: &str) -> impl Iterator<Item = Frame> {
+        self.packets(s)
+            .merge_by(self.messages(s), |a, b| a.marker_offset <= b.marker_offset)
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self {
+            packet_marker_len: 4,
+            message_marker_len: 14,
+            overlapping: false,
+        }
+    }
+}
+
+pub struct Frames {
+    data: Vec<char>,
+    kind: FrameKind,
+    marker_len: usize,
+    overlapping: bool,
+    next_marker: Option<usize>,
+}
+
+impl Frames {
+    fn new(s: &str, kind: FrameKind, marker_len: usize, overlapping: bool) -> Self {
+        let mut frames = Self {
+            data: s.trim_end().chars().collect(),
+            kind,
+            marker_len,
+            overlapping,
+            next_marker: None,
+        };
+        frames.next_marker = frames.find_marker(0);
+        frames
+    }
+
+    // where the first marker at or after from starts
+    fn find_marker(&self, from: usize) -> Option<usize> {
+        find_first_unique_pattern(&self.data[from..], self.marker_len)
+            .map(|end| from + end - self.marker_len)
+    }
+}
+
+impl Iterator for Frames {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let marker_offset = self.next_marker?;
+        let payload_offset = marker_offset + self.marker_len;
+        self.next_marker = self.find_marker(if self.overlapping {
+            marker_offset + 1
+        } else {
+            payload_offset
+        });
+        // an overlapping marker can start before this payload would, leaving it empty
+        let payload_end = self
+            .next_marker
+            .unwrap_or(self.data.len())
+            .max(payload_offset);
+        Some(Frame {
+            kind: self.kind,
+            marker_offset,
+            payload_offset,
+            payload: self.data[payload_offset..payload_end].iter().collect(),
+        })
+    }
+}
+
+// finds where the first run of pattern_len distinct chars ends, in a single pass
+pub fn find_first_unique_pattern(data: &[char], pattern_len: usize) -> Option<usize> {
+    if pattern_len == 0 {
+        return None;
+    }
+    let mut counts = HashMap::new();
+    let mut duplicates = 0;
+    for (i, c) in data.iter().enumerate() {
+        let count = counts.entry(c).or_insert(0);
+        *count += 1;
+        if *count == 2 {
+            duplicates += 1;
+        }
+        if i >= pattern_len {
+            let count = counts.get_mut(&data[i - pattern_len]).unwrap();
+            if *count == 2 {
+                duplicates -= 1;
+            }
+            *count -= 1;
+        }
+        if i + 1 >= pattern_len && duplicates == 0 {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: [(&str, usize, usize); 5] = [
+        ("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 7, 19),
+        ("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23),
+        ("nppdvjthqldpwncqszvftbrmjlhg", 6, 23),
+        ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 10, 29),
+        ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11, 26),
+    ];
+
+    #[test]
+    fn first_frames_match_samples() {
+        let decoder = Decoder::default();
+        for (s, packet, message) in SAMPLES {
+            assert_eq!(
+                Some(packet),
+                decoder.packets(s).next().map(|f| f.payload_offset)
+            );
+            assert_eq!(
+                Some(message),
+                decoder.messages(s).next().map(|f| f.payload_offset)
+            );
+        }
+    }
+
+    #[test]
+    fn frames_split_stream() {
+        let frames = Decoder::default()
+            .packets("aaabcdxxxwxyzqq")
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                Frame {
+                    kind: FrameKind::Packet,
+                    marker_offset: 2,
+                    payload_offset: 6,
+                    payload: "xxx".to_string(),
+                },
+                Frame {
+                    kind: FrameKind::Packet,
+                    marker_offset: 9,
+                    payload_offset: 13,
+                    payload: "qq".to_string(),
+                },
+            ],
+            frames
+        );
+    }
+
+    #[test]
+    fn overlapping_markers() {
+        // every window of "abcdefgh" is unique, so with overlapping on each one is a marker
+        let markers = |decoder: Decoder| {
+            decoder
+                .packets("abcdefgh")
+                .map(|f| (f.marker_offset, f.payload))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![(0, "".to_string()), (4, "".to_string())],
+            markers(Decoder::default())
+        );
+        assert_eq!(
+            vec![
+                (0, "".to_string()),
+                (1, "".to_string()),
+                (2, "".to_string()),
+                (3, "".to_string()),
+                (4, "".to_string()),
+            ],
+            markers(Decoder::default().overlapping(true))
+        );
+        // markers that overlap still leave a payload between them when there is one
+        let frames = Decoder::default()
+            .overlapping(true)
+            .packets("aaabcdxxxwxyzqq")
+            .map(|f| (f.marker_offset, f.payload))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (2, "".to_string()),
+                (3, "xx".to_string()),
+                (9, "".to_string()),
+                (10, "q".to_string()),
+            ],
+            frames
+        );
+    }
+
+    #[test]
+    fn markers_are_searched_from_the_previous_payload() {
+        // "bcde" is unique and starts inside the first marker, so it's passed over for "efgh",
+        // which starts right where the first payload does
+        let data = "abcdefghh".chars().collect::<Vec<_>>();
+        assert_eq!(Some(4), find_first_unique_pattern(&data, 4));
+        assert_eq!(Some(4), find_first_unique_pattern(&data[4..], 4));
+        assert_eq!(None, find_first_unique_pattern(&data[5..], 4));
+        let frames = Decoder::default().packets("abcdefghh").collect::<Vec<_>>();
+        assert_eq!(
+            vec![(0, ""), (4, "h")],
+            frames
+                .iter()
+                .map(|f| (f.marker_offset, f.payload.as_str()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn configurable_lengths() -> Result<(), String> {
+        let decoder = Decoder::new(2, 3)?;
+        let frames = decoder.frames("aabaab").collect::<Vec<_>>();
+        assert_eq!(
+            vec![(FrameKind::Packet, 1, "a"), (FrameKind::Packet, 4, ""),],
+            frames
+                .iter()
+                .map(|f| (f.kind, f.marker_offset, f.payload.as_str()))
+                .collect::<Vec<_>>()
+        );
+        assert!(Decoder::new(0, 14).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn interleaved_frames() {
+        let kinds = Decoder::new(2, 4)
+            .unwrap()
+            .frames("xxabcd")
+            .map(|f| (f.kind, f.marker_offset))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (FrameKind::Packet, 1),
+                (FrameKind::Message, 1),
+                (FrameKind::Packet, 3),
+            ],
+            kinds
+        );
+    }
+}
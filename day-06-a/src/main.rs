@@ -1,33 +1,51 @@
+mod decoder;
+
 use std::{
-    collections::HashSet,
+    env,
     error::Error,
     io::{self, Read},
 };
 
-use substring::Substring;
+use decoder::Decoder;
 
 fn main() -> Result<(), Box<dyn Error>> {
+    // optional marker lengths, e.g. `cargo run -- 4 14`, and whether markers can overlap
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    let overlapping = args.iter().any(|arg| arg == "--overlapping");
+    args.retain(|arg| arg != "--overlapping");
+    let args = args
+        .iter()
+        .map(|arg| arg.parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()?;
+    let decoder = match args.as_slice() {
+        [] => Decoder::default(),
+        [packet_marker_len, message_marker_len] => {
+            Decoder::new(*packet_marker_len, *message_marker_len)?
+        }
+        _ => Err(format!(
+            "expected zero or two marker lengths, got {:?}",
+            args
+        ))?,
+    }
+    .overlapping(overlapping);
+    println!("decoder = {:?}", decoder);
+
     let mut s = String::new();
     io::BufReader::new(io::stdin()).read_to_string(&mut s)?;
     println!("input = {}", s);
 
-    match find_first_unique_pattern(&s, 4) {
-        Some(solution) => println!("solution = {:?}", solution),
+    // the first frame starts right after the first marker
+    match decoder.packets(&s).next() {
+        Some(frame) => println!("solution = {:?}", frame.payload_offset),
         None => println!("no solution"),
     }
 
-    Ok(())
-}
-
-fn find_first_unique_pattern(s: &str, pattern_len: usize) -> Option<usize> {
-    for i in 0..(s.len() - pattern_len) {
-        let possible = s
-            .substring(i, i + pattern_len)
-            .chars()
-            .collect::<HashSet<char>>();
-        if possible.len() == pattern_len {
-            return Some(i + pattern_len);
-        }
+    for frame in decoder.frames(&s) {
+        println!(
+            "{:?} marker at {}, payload at {} = {:?}",
+            frame.kind, frame.marker_offset, frame.payload_offset, frame.payload
+        );
     }
-    return None;
+
+    Ok(())
 }
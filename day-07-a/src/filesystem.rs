@@ -0,0 +1,423 @@
+use std::error::Error;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NodeId(usize);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum NodeKind {
+    Directory { children: Vec<NodeId> },
+    File { size: usize },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Node {
+    name: String,
+    parent: Option<NodeId>,
+    kind: NodeKind,
+    // the total of every file at or under this node, kept up to date as things change
+    #[serde(skip)]
+    size: usize,
+}
+
+impl Node {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn is_directory(&self) -> bool {
+        matches!(self.kind, NodeKind::Directory { .. })
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        match &self.kind {
+            NodeKind::Directory { children } => children,
+            NodeKind::File { .. } => &[],
+        }
+    }
+}
+
+// every node lives in one arena and refers to the others by index, removed nodes leave a hole
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSystem {
+    nodes: Vec<Option<Node>>,
+}
+
+impl FileSystem {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![Some(Node {
+                name: "/".to_string(),
+                parent: None,
+                kind: NodeKind::Directory {
+                    children: Vec::new(),
+                },
+                size: 0,
+            })],
+        }
+    }
+
+    pub fn from_json(s: &str) -> Result<Self, Box<dyn Error>> {
+        let mut result = serde_json::from_str::<FileSystem>(s)?;
+        result.check()?;
+        result.compute_size(result.root());
+        Ok(result)
+    }
+
+    pub fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id.0).and_then(|node| node.as_ref())
+    }
+
+    // ids only come from this filesystem, so a missing one is a bug rather than bad input
+    pub fn node(&self, id: NodeId) -> &Node {
+        self.get(id)
+            .unwrap_or_else(|| panic!("no node with id {:?}", id))
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        self.nodes[id.0]
+            .as_mut()
+            .unwrap_or_else(|| panic!("no node with id {:?}", id))
+    }
+
+    pub fn child(&self, directory: NodeId, name: &str) -> Option<NodeId> {
+        self.node(directory)
+            .children()
+            .iter()
+            .find(|child| self.node(**child).name() == name)
+            .copied()
+    }
+
+    pub fn child_directory(&self, directory: NodeId, name: &str) -> Option<NodeId> {
+        self.child(directory, name)
+            .filter(|child| self.node(*child).is_directory())
+    }
+
+    pub fn directory_by_path<'a, I>(&self, path: I) -> Option<NodeId>
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        let mut result = self.root();
+        for component in path {
+            result = self.child_directory(result, component)?;
+        }
+        Some(result)
+    }
+
+    pub fn path(&self, id: NodeId) -> String {
+        let mut components = Vec::new();
+        let mut current = Some(id);
+        while let Some(id) = current {
+            let node = self.node(id);
+            if node.parent().is_some() {
+                components.push(node.name());
+            }
+            current = node.parent();
+        }
+        components.reverse();
+        format!("/{}", components.join("/"))
+    }
+
+    // the total of every file at or under this node
+    pub fn size(&self, id: NodeId) -> usize {
+        self.node(id).size
+    }
+
+    pub fn create_directory(
+        &mut self,
+        parent: NodeId,
+        name: &str,
+    ) -> Result<NodeId, Box<dyn Error>> {
+        self.add(
+            parent,
+            name,
+            NodeKind::Directory {
+                children: Vec::new(),
+            },
+        )
+    }
+
+    pub fn create_file(
+        &mut self,
+        parent: NodeId,
+        name: &str,
+        size: usize,
+    ) -> Result<NodeId, Box<dyn Error>> {
+        self.add(parent, name, NodeKind::File { size })
+    }
+
+    pub fn get_or_create_directory(
+        &mut self,
+        parent: NodeId,
+        name: &str,
+    ) -> Result<NodeId, Box<dyn Error>> {
+        match self.child(parent, name) {
+            Some(existing) if self.node(existing).is_directory() => {
+                println!("found existing dir {} in {}", name, self.path(parent));
+                Ok(existing)
+            }
+            Some(_) => Err(format!(
+                "{} in {} is already a file",
+                name,
+                self.path(parent)
+            ))?,
+            None => {
+                println!("adding dir {} to {}", name, self.path(parent));
+                self.create_directory(parent, name)
+            }
+        }
+    }
+
+    pub fn get_or_create_file(
+        &mut self,
+        parent: NodeId,
+        name: &str,
+        size: usize,
+    ) -> Result<NodeId, Box<dyn Error>> {
+        match self.child(parent, name) {
+            Some(existing) if !self.node(existing).is_directory() => {
+                println!("found existing file {} in {}", name, self.path(parent));
+                Ok(existing)
+            }
+            Some(_) => Err(format!(
+                "{} in {} is already a directory",
+                name,
+                self.path(parent)
+            ))?,
+            None => {
+                println!("adding file {} to {}", name, self.path(parent));
+                self.create_file(parent, name, size)
+            }
+        }
+    }
+
+    // removes the node and everything under it
+    pub fn remove(&mut self, id: NodeId) -> Result<(), Box<dyn Error>> {
+        let parent = self.node(id).parent().ok_or("can't remove /")?;
+        self.detach(parent, id);
+        self.resize(parent, self.size(id), 0);
+        let mut pending = vec![id];
+        while let Some(id) = pending.pop() {
+            if let Some(node) = self.nodes[id.0].take() {
+                pending.extend_from_slice(node.children());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn move_to(
+        &mut self,
+        id: NodeId,
+        new_parent: NodeId,
+        new_name: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let parent = self.node(id).parent().ok_or("can't move /")?;
+        if !self.node(new_parent).is_directory() {
+            Err(format!("{} is not a directory", self.path(new_parent)))?
+        }
+        let mut ancestor = Some(new_parent);
+        while let Some(current) = ancestor {
+            if current == id {
+                Err(format!("can't move {} inside itself", self.path(id)))?
+            }
+            ancestor = self.node(current).parent();
+        }
+        if self.child(new_parent, new_name).is_some() {
+            Err(format!(
+                "{} already exists in {}",
+                new_name,
+                self.path(new_parent)
+            ))?
+        }
+        self.detach(parent, id);
+        self.resize(parent, self.size(id), 0);
+        self.attach(new_parent, id);
+        self.resize(new_parent, 0, self.size(id));
+        let node = self.node_mut(id);
+        node.name = new_name.to_string();
+        node.parent = Some(new_parent);
+        Ok(())
+    }
+
+    fn add(
+        &mut self,
+        parent: NodeId,
+        name: &str,
+        kind: NodeKind,
+    ) -> Result<NodeId, Box<dyn Error>> {
+        if !self.node(parent).is_directory() {
+            Err(format!("{} is not a directory", self.path(parent)))?
+        }
+        if self.child(parent, name).is_some() {
+            Err(format!("{} already exists in {}", name, self.path(parent)))?
+        }
+        let id = NodeId(self.nodes.len());
+        let size = match kind {
+            NodeKind::Directory { .. } => 0,
+            NodeKind::File { size } => size,
+        };
+        self.nodes.push(Some(Node {
+            name: name.to_string(),
+            parent: Some(parent),
+            kind,
+            size,
+        }));
+        self.attach(parent, id);
+        self.resize(parent, 0, size);
+        Ok(id)
+    }
+
+    // takes removed away from and adds added to the size of this directory and all of its parents
+    fn resize(&mut self, id: NodeId, removed: usize, added: usize) {
+        let mut current = Some(id);
+        while let Some(id) = current {
+            let node = self.node_mut(id);
+            node.size = node.size + added - removed;
+            current = node.parent;
+        }
+    }
+
+    // sizes aren't saved, so they're worked out again bottom up after loading
+    fn compute_size(&mut self, id: NodeId) -> usize {
+        let node = self.node(id);
+        let size = match &node.kind {
+            NodeKind::Directory { children } => children
+                .clone()
+                .iter()
+                .map(|child| self.compute_size(*child))
+                .sum(),
+            NodeKind::File { size } => *size,
+        };
+        self.node_mut(id).size = size;
+        size
+    }
+
+    fn attach(&mut self, parent: NodeId, id: NodeId) {
+        if let NodeKind::Directory { children } = &mut self.node_mut(parent).kind {
+            children.push(id);
+        }
+    }
+
+    fn detach(&mut self, parent: NodeId, id: NodeId) {
+        if let NodeKind::Directory { children } = &mut self.node_mut(parent).kind {
+            children.retain(|child| *child != id);
+        }
+    }
+
+    // makes sure loaded data links up the same way a parsed transcript would
+    fn check(&self) -> Result<(), String> {
+        match self.get(self.root()) {
+            Some(root) if root.is_directory() && root.parent().is_none() => (),
+            _ => Err("the first node has to be the root directory")?,
+        }
+        let mut seen = vec![false; self.nodes.len()];
+        let mut pending = vec![self.root()];
+        while let Some(id) = pending.pop() {
+            if std::mem::replace(&mut seen[id.0], true) {
+                Err(format!("node {:?} is reachable more than once", id))?
+            }
+            for child in self.node(id).children() {
+                match self.get(*child) {
+                    Some(node) if node.parent() == Some(id) => pending.push(*child),
+                    _ => Err(format!("bad child {:?} of {:?}", child, id))?,
+                }
+            }
+        }
+        for (i, node) in self.nodes.iter().enumerate() {
+            if node.is_some() && !seen[i] {
+                Err(format!("node {} isn't reachable from the root", i))?
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Result<FileSystem, Box<dyn Error>> {
+        let mut fs = FileSystem::new();
+        let a = fs.create_directory(fs.root(), "a")?;
+        let b = fs.create_directory(a, "b")?;
+        fs.create_file(b, "c.txt", 10)?;
+        fs.create_file(a, "d", 5)?;
+        fs.create_directory(fs.root(), "e")?;
+        Ok(fs)
+    }
+
+    #[test]
+    fn paths_and_sizes() -> Result<(), Box<dyn Error>> {
+        let fs = sample()?;
+        let b = fs
+            .directory_by_path(["a", "b"].into_iter())
+            .ok_or("missing b")?;
+        assert_eq!("/a/b", fs.path(b));
+        assert_eq!("/", fs.path(fs.root()));
+        assert_eq!(
+            "/a/b/c.txt",
+            fs.path(fs.child(b, "c.txt").ok_or("missing c")?)
+        );
+        assert_eq!(15, fs.size(fs.root()));
+        assert_eq!(None, fs.directory_by_path(["a", "d"].into_iter()));
+        Ok(())
+    }
+
+    #[test]
+    fn remove_and_move() -> Result<(), Box<dyn Error>> {
+        let mut fs = sample()?;
+        let a = fs.child(fs.root(), "a").ok_or("missing a")?;
+        let b = fs.child(a, "b").ok_or("missing b")?;
+        let e = fs.child(fs.root(), "e").ok_or("missing e")?;
+        assert!(fs.move_to(a, b, "x").is_err());
+        fs.move_to(b, e, "f")?;
+        assert_eq!("/e/f", fs.path(b));
+        assert_eq!(10, fs.size(e));
+        fs.remove(e)?;
+        assert!(fs.get(b).is_none());
+        assert_eq!(5, fs.size(fs.root()));
+        assert!(fs.remove(fs.root()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn json_round_trip() -> Result<(), Box<dyn Error>> {
+        let mut fs = sample()?;
+        fs.remove(fs.child(fs.root(), "e").ok_or("missing e")?)?;
+        let loaded = FileSystem::from_json(&fs.to_json()?)?;
+        assert_eq!(fs.to_json()?, loaded.to_json()?);
+        let c = loaded
+            .directory_by_path(["a", "b"].into_iter())
+            .and_then(|b| loaded.child(b, "c.txt"))
+            .ok_or("missing c")?;
+        assert_eq!("/a/b/c.txt", loaded.path(c));
+        assert_eq!(15, loaded.size(loaded.root()));
+        assert_eq!(10, loaded.size(c));
+        Ok(())
+    }
+
+    #[test]
+    fn bad_json() {
+        assert!(FileSystem::from_json("{\"nodes\": []}").is_err());
+        assert!(FileSystem::from_json(
+            r#"{"nodes": [
+                {"name": "/", "parent": null, "kind": {"Directory": {"children": [1, 1]}}},
+                {"name": "a", "parent": 0, "kind": {"File": {"size": 1}}}
+            ]}"#
+        )
+        .is_err());
+    }
+}
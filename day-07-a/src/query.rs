@@ -0,0 +1,457 @@
+use std::fmt::Write;
+
+use crate::filesystem::{FileSystem, NodeId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Directory,
+    File,
+}
+
+#[derive(Debug, Clone)]
+pub struct UsageEntry {
+    pub path: String,
+    pub name: String,
+    pub kind: Kind,
+    pub depth: usize,
+    // for directories this is the total of everything underneath
+    pub size: usize,
+    children: Vec<usize>,
+}
+
+#[derive(Default)]
+pub struct Query {
+    pub min_size: Option<usize>,
+    pub max_size: Option<usize>,
+    pub name: Option<glob::Pattern>,
+    pub kind: Option<Kind>,
+}
+
+#[derive(Debug)]
+pub struct Plan<'a> {
+    pub required: usize,
+    pub freed: usize,
+    pub directories: Vec<&'a UsageEntry>,
+}
+
+// a snapshot of the tree in the order it prints, with the sizes the filesystem keeps
+pub struct DiskUsage {
+    // pre-order, the root is always first
+    entries: Vec<UsageEntry>,
+}
+
+impl DiskUsage {
+    pub fn new(filesystem: &FileSystem) -> Self {
+        fn visit(filesystem: &FileSystem, id: NodeId, depth: usize, entries: &mut Vec<UsageEntry>) {
+            let node = filesystem.node(id);
+            let index = entries.len();
+            entries.push(UsageEntry {
+                path: filesystem.path(id),
+                name: node.name().to_string(),
+                kind: Kind::Directory,
+                depth,
+                size: filesystem.size(id),
+                children: Vec::new(),
+            });
+            // directories first, then files, each in the order they were found
+            let (dirs, files): (Vec<NodeId>, Vec<NodeId>) = node
+                .children()
+                .iter()
+                .partition(|child| filesystem.node(**child).is_directory());
+            for child in dirs {
+                let child_index = entries.len();
+                visit(filesystem, child, depth + 1, entries);
+                entries[index].children.push(child_index);
+            }
+            for child in files {
+                let child_index = entries.len();
+                entries[index].children.push(child_index);
+                entries.push(UsageEntry {
+                    path: filesystem.path(child),
+                    name: filesystem.node(child).name().to_string(),
+                    kind: Kind::File,
+                    depth: depth + 1,
+                    size: filesystem.size(child),
+                    children: Vec::new(),
+                });
+            }
+        }
+        let mut entries = Vec::new();
+        visit(filesystem, filesystem.root(), 0, &mut entries);
+        Self { entries }
+    }
+
+    pub fn root(&self) -> &UsageEntry {
+        &self.entries[0]
+    }
+
+    // directories no deeper than max_depth below the root, children before their parents like du
+    pub fn du(&self, max_depth: Option<usize>) -> Vec<&UsageEntry> {
+        fn visit<'a>(
+            usage: &'a DiskUsage,
+            index: usize,
+            max_depth: Option<usize>,
+            results: &mut Vec<&'a UsageEntry>,
+        ) {
+            let entry = &usage.entries[index];
+            if entry.kind != Kind::Directory || max_depth.is_some_and(|d| entry.depth > d) {
+                return;
+            }
+            for child in entry.children.iter() {
+                visit(usage, *child, max_depth, results);
+            }
+            results.push(entry);
+        }
+        let mut results = Vec::new();
+        visit(self, 0, max_depth, &mut results);
+        results
+    }
+
+    pub fn find(&self, query: &Query) -> Vec<&UsageEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| query.min_size.is_none_or(|size| entry.size >= size))
+            .filter(|entry| query.max_size.is_none_or(|size| entry.size <= size))
+            .filter(|entry| query.kind.is_none_or(|kind| entry.kind == kind))
+            .filter(|entry| {
+                query
+                    .name
+                    .as_ref()
+                    .is_none_or(|pattern| pattern.matches(&entry.name))
+            })
+            .collect()
+    }
+
+    pub fn tree(&self) -> String {
+        let mut result = String::new();
+        for entry in self.entries.iter() {
+            writeln!(
+                result,
+                "{}- {} ({}, size={})",
+                "  ".repeat(entry.depth),
+                entry.name,
+                match entry.kind {
+                    Kind::Directory => "dir",
+                    Kind::File => "file",
+                },
+                entry.size
+            )
+            .unwrap();
+        }
+        result
+    }
+
+    // how much more has to be freed so that there's at least needed bytes available
+    pub fn required(&self, capacity: usize, needed: usize) -> Result<usize, String> {
+        let used = self.root().size;
+        if used > capacity {
+            Err(format!(
+                "{} bytes used is more than the capacity {}",
+                used, capacity
+            ))?
+        }
+        if needed > capacity {
+            Err(format!(
+                "can't free {} bytes on a disk of {}",
+                needed, capacity
+            ))?
+        }
+        Ok(needed.saturating_sub(capacity - used))
+    }
+
+    // the directories to delete that free at least enough space while deleting as little as possible
+    pub fn plan(&self, capacity: usize, needed: usize) -> Result<Plan<'_>, String> {
+        let required = self.required(capacity, needed)?;
+        if required == 0 {
+            return Ok(Plan {
+                required,
+                freed: 0,
+                directories: Vec::new(),
+            });
+        }
+
+        // directories in pre-order, deleting one skips everything up to the end of its subtree
+        let mut order = Vec::new();
+        let mut subtree_end = Vec::new();
+        fn visit(usage: &DiskUsage, index: usize, order: &mut Vec<usize>, ends: &mut Vec<usize>) {
+            let position = order.len();
+            order.push(index);
+            ends.push(0);
+            for child in usage.entries[index].children.iter() {
+                if usage.entries[*child].kind == Kind::Directory {
+                    visit(usage, *child, order, ends);
+                }
+            }
+            ends[position] = order.len();
+        }
+        visit(self, 0, &mut order, &mut subtree_end);
+        let sizes = order
+            .iter()
+            .map(|index| self.entries[*index].size)
+            .collect::<Vec<_>>();
+
+        let mut search = PlanSearch::new(sizes, subtree_end, required);
+        search.visit(0, 0);
+        let (freed, positions) = search
+            .best
+            .ok_or(format!("no set of directories frees {} bytes", required))?;
+        Ok(Plan {
+            required,
+            freed,
+            directories: positions
+                .iter()
+                .map(|position| &self.entries[order[*position]])
+                .collect(),
+        })
+    }
+}
+
+// tries every set of directories where none is inside another, going through them in pre-order
+// and either deleting each one, which skips its subtree, or not
+struct PlanSearch {
+    sizes: Vec<usize>,
+    subtree_end: Vec<usize>,
+    // the most that can be freed by deleting directories from each position onwards
+    most: Vec<usize>,
+    required: usize,
+    chosen: Vec<usize>,
+    best: Option<(usize, Vec<usize>)>,
+}
+
+impl PlanSearch {
+    fn new(sizes: Vec<usize>, subtree_end: Vec<usize>, required: usize) -> Self {
+        // a directory is at least as big as everything in it, so the most comes from deleting it
+        let mut most = vec![0; sizes.len() + 1];
+        for i in (0..sizes.len()).rev() {
+            most[i] = sizes[i] + most[subtree_end[i]];
+        }
+        Self {
+            sizes,
+            subtree_end,
+            most,
+            required,
+            chosen: Vec::new(),
+            best: None,
+        }
+    }
+
+    fn visit(&mut self, position: usize, freed: usize) {
+        if freed >= self.required {
+            if self.best.as_ref().is_none_or(|(best, _)| freed < *best) {
+                self.best = Some((freed, self.chosen.clone()));
+            }
+            return;
+        }
+        // give up once nothing left can free enough, or once nothing could do better
+        let exact = self
+            .best
+            .as_ref()
+            .is_some_and(|(best, _)| *best == self.required);
+        if position == self.sizes.len() || freed + self.most[position] < self.required || exact {
+            return;
+        }
+        let with = freed + self.sizes[position];
+        // anything after this that would take it past the best so far isn't worth trying
+        if self.best.as_ref().is_none_or(|(best, _)| with < *best) {
+            self.chosen.push(position);
+            self.visit(self.subtree_end[position], with);
+            self.chosen.pop();
+        }
+        self.visit(position + 1, freed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::shell::Shell;
+
+    fn sample() -> Result<DiskUsage, Box<dyn std::error::Error>> {
+        Ok(DiskUsage::new(
+            Shell::replay(
+                &mut r"$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k"
+                    .as_bytes(),
+                None,
+            )?
+            .filesystem(),
+        ))
+    }
+
+    fn paths(entries: Vec<&UsageEntry>) -> Vec<(&str, usize)> {
+        entries
+            .iter()
+            .map(|entry| (entry.path.as_str(), entry.size))
+            .collect()
+    }
+
+    #[test]
+    fn du() -> Result<(), Box<dyn std::error::Error>> {
+        let usage = sample()?;
+        assert_eq!(
+            vec![
+                ("/a/e", 584),
+                ("/a", 94853),
+                ("/d", 24933642),
+                ("/", 48381165)
+            ],
+            paths(usage.du(None))
+        );
+        assert_eq!(vec![("/", 48381165)], paths(usage.du(Some(0))));
+        Ok(())
+    }
+
+    #[test]
+    fn find() -> Result<(), Box<dyn std::error::Error>> {
+        let usage = sample()?;
+        assert_eq!(
+            vec![("/d/d.log", 8033020), ("/d/d.ext", 5626152)],
+            paths(usage.find(&Query {
+                min_size: Some(1000000),
+                name: Some(glob::Pattern::new("d.*")?),
+                ..Default::default()
+            }))
+        );
+        assert_eq!(
+            vec![("/a", 94853), ("/a/e", 584)],
+            paths(usage.find(&Query {
+                max_size: Some(100000),
+                kind: Some(Kind::Directory),
+                ..Default::default()
+            }))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn tree() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(
+            r"- / (dir, size=48381165)
+  - a (dir, size=94853)
+    - e (dir, size=584)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - d (dir, size=24933642)
+    - j (file, size=4060174)
+    - d.log (file, size=8033020)
+    - d.ext (file, size=5626152)
+    - k (file, size=7214296)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+",
+            sample()?.tree()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn plan() -> Result<(), Box<dyn std::error::Error>> {
+        let usage = sample()?;
+        let plan = usage.plan(70000000, 30000000)?;
+        assert_eq!(8381165, plan.required);
+        assert_eq!(vec![("/d", 24933642)], paths(plan.directories));
+
+        // a and d together are the closest fit when just over d's size is needed
+        let plan = usage.plan(48381165, 24933642 + 1000)?;
+        assert_eq!(
+            vec![("/a", 94853), ("/d", 24933642)],
+            paths(plan.directories)
+        );
+        assert_eq!(24933642 + 94853, plan.freed);
+
+        assert!(usage.plan(70000000, 0)?.directories.is_empty());
+        assert!(usage.plan(70000000, 80000000).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn plan_is_smallest() -> Result<(), Box<dyn std::error::Error>> {
+        let usage = DiskUsage::new(
+            Shell::replay(
+                &mut r"$ cd /
+$ ls
+dir a
+dir b
+1 x
+$ cd a
+$ ls
+dir c
+dir d
+2 y
+$ cd c
+$ ls
+3 z
+$ cd ..
+$ cd d
+$ ls
+5 z
+$ cd /
+$ cd b
+$ ls
+dir e
+7 y
+$ cd e
+$ ls
+11 z"
+                    .as_bytes(),
+                None,
+            )?
+            .filesystem(),
+        );
+        let directories = usage.du(None);
+        let nested = |a: &UsageEntry, b: &UsageEntry| {
+            a.path != b.path && (a.path == "/" || b.path.starts_with(&format!("{}/", a.path)))
+        };
+        // every set of directories that doesn't have one inside another
+        let mut totals = Vec::new();
+        for bits in 0..1 << directories.len() {
+            let chosen = (0..directories.len())
+                .filter(|i| bits & (1 << i) != 0)
+                .map(|i| directories[i])
+                .collect::<Vec<_>>();
+            if chosen.iter().all(|a| chosen.iter().all(|b| !nested(a, b))) {
+                totals.push(chosen.iter().map(|entry| entry.size).sum::<usize>());
+            }
+        }
+        for needed in 1..=29 {
+            let plan = usage.plan(29, needed)?;
+            let expected = totals.iter().filter(|total| **total >= needed).min();
+            assert_eq!(expected, Some(&plan.freed));
+            assert_eq!(
+                plan.freed,
+                plan.directories
+                    .iter()
+                    .map(|entry| entry.size)
+                    .sum::<usize>()
+            );
+            for a in plan.directories.iter() {
+                assert!(plan.directories.iter().all(|b| !nested(a, b)));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,407 @@
+use std::{
+    error::Error,
+    io::{BufRead, BufReader},
+};
+
+use crate::filesystem::{FileSystem, NodeId};
+
+// one line of a transcript, as the shell and the validator both read it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line<'a> {
+    Command(&'a str, Vec<&'a str>),
+    Directory(&'a str),
+    File(usize, &'a str),
+}
+
+pub struct LineParser {
+    command_regex: regex::Regex,
+    dir_regex: regex::Regex,
+    file_regex: regex::Regex,
+}
+
+impl LineParser {
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            command_regex: regex::Regex::new(r"^\$ (\S+)((?: +\S+)*)\s*$")?,
+            dir_regex: regex::Regex::new(r"^dir (.+)$")?,
+            file_regex: regex::Regex::new(r"^([0-9]+) (.+)$")?,
+        })
+    }
+
+    pub fn parse<'a>(&self, line: &'a str) -> Result<Line<'a>, Box<dyn Error>> {
+        if let Some(captures) = self.command_regex.captures(line) {
+            let command = captures.get(1).unwrap().as_str();
+            let args = captures.get(2).unwrap().as_str();
+            Ok(Line::Command(command, args.split_whitespace().collect()))
+        } else if let Some(captures) = self.dir_regex.captures(line) {
+            Ok(Line::Directory(captures.get(1).unwrap().as_str()))
+        } else if let Some(captures) = self.file_regex.captures(line) {
+            let size = captures[1].parse::<usize>()?;
+            Ok(Line::File(size, captures.get(2).unwrap().as_str()))
+        } else {
+            Err(format!("unrecognized line: {}", line))?
+        }
+    }
+}
+
+pub struct Shell {
+    filesystem: FileSystem,
+    current_working_directory: Vec<String>,
+    // the directory whose contents are being printed, if the last command was an ls
+    listing: Option<NodeId>,
+    parser: LineParser,
+}
+
+impl Shell {
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            filesystem: FileSystem::new(),
+            current_working_directory: Vec::new(),
+            listing: None,
+            parser: LineParser::new()?,
+        })
+    }
+
+    // runs every line of the transcript, or only up to and including the given 1-based line number
+    pub fn replay(
+        r: &mut impl std::io::Read,
+        until: Option<usize>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut result = Shell::new()?;
+        for (i, line) in BufReader::new(r).lines().enumerate() {
+            let line_number = i + 1;
+            if until.is_some_and(|until| line_number > until) {
+                break;
+            }
+            let line = line?;
+            result
+                .execute(&line)
+                .map_err(|e| format!("line {}: {}", line_number, e))?;
+        }
+        Ok(result)
+    }
+
+    pub fn filesystem(&self) -> &FileSystem {
+        &self.filesystem
+    }
+
+    pub fn into_filesystem(self) -> FileSystem {
+        self.filesystem
+    }
+
+    pub fn current_working_directory(&self) -> String {
+        format!("/{}", self.current_working_directory.join("/"))
+    }
+
+    pub fn execute(&mut self, line: &str) -> Result<(), Box<dyn Error>> {
+        let parsed = self.parser.parse(line);
+        match (parsed, self.listing) {
+            (Ok(Line::Command(command, args)), _) => {
+                self.listing = None;
+                match command {
+                    "cd" => self.cd(&args),
+                    "ls" => self.ls(&args),
+                    "mkdir" => self.mkdir(&args),
+                    "rm" => self.rm(&args),
+                    "mv" => self.mv(&args),
+                    "touch" => self.touch(&args),
+                    _ => Err(format!("unrecognized command: {}", command))?,
+                }
+            }
+            (_, None) => Err(format!("output without a preceding ls: {}", line))?,
+            (Ok(Line::Directory(name)), Some(listing)) => {
+                self.filesystem.get_or_create_directory(listing, name)?;
+                Ok(())
+            }
+            (Ok(Line::File(size, name)), Some(listing)) => {
+                self.filesystem.get_or_create_file(listing, name, size)?;
+                Ok(())
+            }
+            (Err(e), Some(_)) => Err(e),
+        }
+    }
+
+    fn cd(&mut self, args: &[&str]) -> Result<(), Box<dyn Error>> {
+        let path = match args {
+            [path] => self.resolve(path),
+            _ => Err(format!("cd: expected exactly one path, got {:?}", args))?,
+        };
+        self.directory(&path).map_err(|e| format!("cd: {}", e))?;
+        self.current_working_directory = path;
+        println!("working dir is now {}", self.current_working_directory());
+        Ok(())
+    }
+
+    fn ls(&mut self, args: &[&str]) -> Result<(), Box<dyn Error>> {
+        if !args.is_empty() {
+            Err(format!("ls: unexpected arguments {:?}", args))?
+        }
+        self.listing = Some(self.directory(&self.current_working_directory)?);
+        Ok(())
+    }
+
+    fn mkdir(&mut self, args: &[&str]) -> Result<(), Box<dyn Error>> {
+        let (parents, paths) = Shell::split_flag(args, "-p");
+        if paths.is_empty() {
+            Err("mkdir: missing path")?
+        }
+        for path in paths {
+            let path = self.resolve(path);
+            let (name, parent_path) = path.split_last().ok_or("mkdir: / already exists")?;
+            let mut parent = self.filesystem.root();
+            for component in parent_path {
+                parent = if parents {
+                    self.filesystem.get_or_create_directory(parent, component)?
+                } else {
+                    self.filesystem
+                        .child_directory(parent, component)
+                        .ok_or(format!("mkdir: no such directory {}", component))?
+                };
+            }
+            match self.filesystem.child(parent, name) {
+                Some(existing) if parents && self.filesystem.node(existing).is_directory() => (),
+                Some(_) => Err(format!("mkdir: {} already exists", name))?,
+                None => {
+                    self.filesystem.create_directory(parent, name)?;
+                }
+            };
+        }
+        Ok(())
+    }
+
+    fn rm(&mut self, args: &[&str]) -> Result<(), Box<dyn Error>> {
+        let (recursive, paths) = Shell::split_flag(args, "-r");
+        if paths.is_empty() {
+            Err("rm: missing path")?
+        }
+        for path in paths {
+            let path = self.resolve(path);
+            let (name, parent_path) = path.split_last().ok_or("rm: can't remove /")?;
+            if self.current_working_directory.starts_with(&path) {
+                Err(format!("rm: {} contains the working directory", name))?
+            }
+            let parent = self.directory(parent_path)?;
+            match self.filesystem.child(parent, name) {
+                None => Err(format!("rm: no such file or directory {}", name))?,
+                Some(existing) if !recursive && self.filesystem.node(existing).is_directory() => {
+                    Err(format!("rm: {} is a directory", name))?
+                }
+                Some(existing) => self.filesystem.remove(existing)?,
+            };
+        }
+        Ok(())
+    }
+
+    fn mv(&mut self, args: &[&str]) -> Result<(), Box<dyn Error>> {
+        let (source, destination) = match args {
+            [source, destination] => (self.resolve(source), self.resolve(destination)),
+            _ => Err(format!(
+                "mv: expected a source and destination, got {:?}",
+                args
+            ))?,
+        };
+        let (name, source_parent_path) = source.split_last().ok_or("mv: can't move /")?;
+        let source_parent = self.directory(source_parent_path)?;
+        let node = self
+            .filesystem
+            .child(source_parent, name)
+            .ok_or(format!("mv: no such file or directory {}", name))?;
+
+        // moving onto an existing directory puts the source inside it, otherwise it's a rename
+        let target = if self.directory(&destination).is_ok() {
+            let mut result = destination;
+            result.push(name.clone());
+            result
+        } else {
+            destination
+        };
+        let (new_name, target_parent_path) = target.split_last().ok_or("mv: can't replace /")?;
+        if target.starts_with(&source) {
+            Err(format!("mv: can't move {} inside itself", name))?
+        }
+        let target_parent = self.directory(target_parent_path)?;
+        if self.filesystem.child(target_parent, new_name).is_some() {
+            Err(format!("mv: {} already exists", new_name))?
+        }
+        self.filesystem
+            .move_to(node, target_parent, new_name)
+            .map_err(|e| format!("mv: {}", e))?;
+
+        if self.current_working_directory.starts_with(&source) {
+            let mut current_working_directory = target.clone();
+            current_working_directory
+                .extend_from_slice(&self.current_working_directory[source.len()..]);
+            self.current_working_directory = current_working_directory;
+        }
+        Ok(())
+    }
+
+    fn touch(&mut self, args: &[&str]) -> Result<(), Box<dyn Error>> {
+        if args.is_empty() {
+            Err("touch: missing path")?
+        }
+        for path in args {
+            let path = self.resolve(path);
+            let (name, parent_path) = path.split_last().ok_or("touch: / is a directory")?;
+            let parent = self.directory(parent_path)?;
+            if self.filesystem.child(parent, name).is_none() {
+                self.filesystem.create_file(parent, name, 0)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve(&self, path: &str) -> Vec<String> {
+        resolve_path(&self.current_working_directory, path)
+    }
+
+    fn directory(&self, path: &[String]) -> Result<NodeId, Box<dyn Error>> {
+        Ok(self
+            .filesystem
+            .directory_by_path(path.iter().map(|s| s.as_str()))
+            .ok_or(format!("no such directory /{}", path.join("/")))?)
+    }
+
+    fn split_flag<'a>(args: &[&'a str], flag: &str) -> (bool, Vec<&'a str>) {
+        (
+            args.contains(&flag),
+            args.iter().filter(|arg| **arg != flag).copied().collect(),
+        )
+    }
+}
+
+// turns an absolute or relative path into components from the root, applying any . and ..
+pub fn resolve_path(current_working_directory: &[String], path: &str) -> Vec<String> {
+    let mut result = if path.starts_with('/') {
+        Vec::new()
+    } else {
+        current_working_directory.to_vec()
+    };
+    for component in path.split('/') {
+        match component {
+            "" | "." => (),
+            ".." => {
+                result.pop();
+            }
+            _ => result.push(component.to_string()),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::query::DiskUsage;
+
+    fn replay(s: &str) -> Result<Shell, Box<dyn Error>> {
+        Shell::replay(&mut s.as_bytes(), None)
+    }
+
+    #[test]
+    fn paths() -> Result<(), Box<dyn Error>> {
+        let shell = replay(
+            r"$ ls
+dir a
+dir c
+$ cd a
+$ ls
+dir b
+$ cd /a/b/../../c",
+        )?;
+        assert_eq!("/c", shell.current_working_directory());
+        Ok(())
+    }
+
+    #[test]
+    fn cd_into_unlisted_directory() {
+        let result = replay(
+            r"$ cd /
+$ ls
+dir a
+$ cd b",
+        );
+        assert_eq!(
+            "line 4: cd: no such directory /b",
+            result.err().unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn parsing_lines() -> Result<(), Box<dyn Error>> {
+        let parser = LineParser::new()?;
+        assert_eq!(
+            Line::Command("mkdir", vec!["-p", "a/b"]),
+            parser.parse("$ mkdir  -p a/b ")?
+        );
+        assert_eq!(Line::Directory("a b"), parser.parse("dir a b")?);
+        assert_eq!(Line::File(123, "b.txt"), parser.parse("123 b.txt")?);
+        assert_eq!(
+            "unrecognized line: 12x b.txt",
+            parser.parse("12x b.txt").err().unwrap().to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn output_without_ls() {
+        assert!(replay("$ cd /\n123 a").is_err());
+    }
+
+    #[test]
+    fn commands() -> Result<(), Box<dyn Error>> {
+        let shell = replay(
+            r"$ mkdir -p /a/b/c
+$ touch /a/b/c/x
+$ ls
+10 y
+$ mv y a/b
+$ mv a/b/c d
+$ rm a/b/y
+$ cd d
+$ touch z",
+        )?;
+        assert_eq!("/d", shell.current_working_directory());
+        assert_eq!(
+            r"- / (dir, size=0)
+  - a (dir, size=0)
+    - b (dir, size=0)
+  - d (dir, size=0)
+    - x (file, size=0)
+    - z (file, size=0)
+",
+            DiskUsage::new(shell.filesystem()).tree()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn command_errors() -> Result<(), Box<dyn Error>> {
+        assert!(replay("$ mkdir a/b").is_err());
+        assert!(replay("$ mkdir a\n$ mkdir a").is_err());
+        assert!(replay("$ mkdir a\n$ rm a").is_err());
+        assert!(replay("$ mkdir a\n$ rm -r a").is_ok());
+        assert!(replay("$ mkdir -p a/b\n$ mv a a/b").is_err());
+        assert!(replay("$ mkdir a\n$ cd a\n$ rm -r /a").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn moving_the_working_directory() -> Result<(), Box<dyn Error>> {
+        let shell = replay("$ mkdir -p a/b\n$ cd a/b\n$ mv /a /c")?;
+        assert_eq!("/c/b", shell.current_working_directory());
+        Ok(())
+    }
+
+    #[test]
+    fn state_at_line() -> Result<(), Box<dyn Error>> {
+        let transcript = "$ mkdir a\n$ mkdir b\n$ rm -r a";
+        let shell = Shell::replay(&mut transcript.as_bytes(), Some(2))?;
+        assert_eq!(
+            "- / (dir, size=0)\n  - a (dir, size=0)\n  - b (dir, size=0)\n",
+            DiskUsage::new(shell.filesystem()).tree()
+        );
+        Ok(())
+    }
+}
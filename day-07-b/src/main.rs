@@ -1,262 +1,164 @@
+mod filesystem;
+mod query;
+mod shell;
+mod validate;
+
 use std::{
-    cell::RefCell,
+    collections::HashMap,
+    env,
     error::Error,
-    io::{self, BufRead, BufReader},
-    rc::Rc,
+    fs,
+    io::{self, BufReader},
 };
 
-trait Entry {
-    fn name(&self) -> &str;
-    fn size(&self) -> usize;
-}
+use filesystem::FileSystem;
+use query::{DiskUsage, Kind, Query, UsageEntry};
+use shell::Shell;
+use validate::validate;
 
-struct File {
-    name: String,
-    size: usize,
-}
+const FILESYSTEM_CAPACITY: usize = 70000000;
+const NEEDED_SPACE: usize = 30000000;
 
-impl File {
-    fn new(name: &str, size: usize) -> File {
-        File {
-            name: name.to_string(),
-            size: size,
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut r = BufReader::new(io::stdin());
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+    match args.as_slice() {
+        [] => {
+            do_it(&mut r, FILESYSTEM_CAPACITY, NEEDED_SPACE)?;
         }
-    }
-}
-
-impl Entry for File {
-    fn name(&self) -> &str {
-        &self.name
-    }
-
-    fn size(&self) -> usize {
-        self.size
-    }
-}
-
-struct Directory {
-    name: String,
-    child_dirs: Vec<Rc<RefCell<Directory>>>,
-    child_files: Vec<Rc<File>>,
-}
-
-impl Directory {
-    fn new(name: &str) -> Rc<RefCell<Directory>> {
-        Rc::new(RefCell::new(Directory {
-            name: name.to_string(),
-            child_dirs: Vec::new(),
-            child_files: Vec::new(),
-        }))
-    }
-
-    fn get_or_create_child_directory_by_name(
-        &mut self,
-        name: &str,
-    ) -> Result<Rc<RefCell<Directory>>, Box<dyn Error>> {
-        if let Some(existing) = self
-            .child_dirs
-            .iter()
-            .find(|child| child.borrow().name() == name)
-        {
-            println!("found existing dir {} in {}", name, self.name);
-            Ok(existing.clone())
-        } else {
-            println!("adding dir {} to {}", name, self.name);
-            let result = Directory::new(name);
-            self.child_dirs.push(result.clone());
-            Ok(result)
+        // the filesystem as it was after that line of the transcript
+        ["state", line_number] => {
+            let shell = Shell::replay(&mut r, Some(line_number.parse()?))?;
+            println!("working dir = {}", shell.current_working_directory());
+            print!("{}", DiskUsage::new(shell.filesystem()).tree());
         }
-    }
-
-    fn get_or_create_child_file_by_name(
-        &mut self,
-        name: &str,
-        size: usize,
-    ) -> Result<Rc<File>, Box<dyn Error>> {
-        if let Some(existing) = self.child_files.iter().find(|child| child.name() == name) {
-            println!("found existing file {} in {}", name, self.name);
-            Ok(existing.clone())
-        } else {
-            println!("adding file {} to {}", name, self.name);
-            let result = Rc::new(File::new(name, size));
-            self.child_files.push(result.clone());
-            Ok(result)
+        ["validate"] => {
+            let diagnostics = validate(&mut r)?;
+            for diagnostic in diagnostics.iter() {
+                println!("{}", diagnostic);
+            }
+            println!("{} problems found", diagnostics.len());
         }
-    }
-
-    fn get_or_create_child_directory_by_path<'a, I>(
-        d: Rc<RefCell<Directory>>,
-        path: I,
-    ) -> Result<Rc<RefCell<Directory>>, Box<dyn Error>>
-    where
-        I: Iterator<Item = &'a str>,
-    {
-        let mut result = d;
-        for component in path {
-            result = result
-                .clone()
-                .borrow_mut()
-                .get_or_create_child_directory_by_name(component)?;
+        // keeps the parsed filesystem so it can be queried later without the transcript
+        ["save", path] => {
+            let filesystem = Shell::replay(&mut r, None)?.into_filesystem();
+            fs::write(path, filesystem.to_json()?)?;
+            println!("saved to {}", path);
         }
-        Ok(result)
-    }
-}
-
-impl Entry for Directory {
-    fn name(&self) -> &str {
-        &self.name
-    }
-
-    fn size(&self) -> usize {
-        self.child_dirs
-            .iter()
-            .map(|child| child.borrow().size())
-            .sum::<usize>()
-            + self
-                .child_files
-                .iter()
-                .map(|child| child.size())
-                .sum::<usize>()
-    }
-}
-
-fn main() -> Result<(), Box<dyn Error>> {
-    let mut r = BufReader::new(io::stdin());
-    do_it(&mut r)?;
+        ["load", path, rest @ ..] => {
+            let filesystem = FileSystem::from_json(&fs::read_to_string(path)?)?;
+            match rest {
+                [] => {
+                    solve(&filesystem, FILESYSTEM_CAPACITY, NEEDED_SPACE)?;
+                }
+                [command, flags @ ..] => query(&filesystem, command, flags)?,
+            };
+        }
+        [command, flags @ ..] => query(
+            &Shell::replay(&mut r, None)?.into_filesystem(),
+            command,
+            flags,
+        )?,
+    };
     Ok(())
 }
 
-fn do_it(r: &mut impl std::io::Read) -> Result<Rc<RefCell<Directory>>, Box<dyn Error>> {
-    let cd_regex = regex::Regex::new(r"^\$ cd (.+)$")?;
-    let ls_regex = regex::Regex::new(r"^\$ ls$")?;
-    let dir_regex = regex::Regex::new(r"^dir (.+)$")?;
-    let file_regex = regex::Regex::new(r"([0-9]+) (.+)")?;
-
-    let mut current_working_directory = Vec::<String>::new();
-    let root_directory = Directory::new("/");
-
-    for line in BufReader::new(r).lines() {
-        match line {
-            Ok(line) => {
-                if let Some(captures) = cd_regex.captures(&line) {
-                    let name = &captures[1];
-                    match name {
-                        "/" => {
-                            println!("cmd is go to root");
-                            current_working_directory.clear();
-                        }
-                        ".." => {
-                            println!("cmd is go up one");
-                            current_working_directory.pop();
-                        }
-                        _ => {
-                            println!("cmd is go to dir = {}", name);
-                            current_working_directory.push(name.clone().to_string());
-                            Directory::get_or_create_child_directory_by_path(
-                                root_directory.clone(),
-                                current_working_directory.iter().map(|s| s.as_str()),
-                            )?;
-                        }
-                    };
-                    println!("working dir is now {:?}", current_working_directory);
-                    Ok(())
-                } else if ls_regex.is_match(&line) {
-                    println!("cmd is ls");
-                    Ok(())
-                } else if let Some(captures) = dir_regex.captures(&line) {
-                    let name = &captures[1];
-                    println!("line is dir = {}", name);
-                    Ok(())
-                } else if let Some(captures) = file_regex.captures(&line) {
-                    let size = captures[1].parse::<usize>()?;
-                    let name = &captures[2];
-                    let file = Directory::get_or_create_child_directory_by_path(
-                        root_directory.clone(),
-                        current_working_directory.iter().map(|s| s.as_str()),
-                    )?
-                    .borrow_mut()
-                    .get_or_create_child_file_by_name(name, size)?;
-                    println!(
-                        "line is file name = {}, size = {}",
-                        file.name(),
-                        file.size()
-                    );
-                    Ok(())
-                } else {
-                    Err(format!("unrecognized line: {}", line))
-                }
+fn query(filesystem: &FileSystem, command: &str, flags: &[&str]) -> Result<(), Box<dyn Error>> {
+    let flags = parse_flags(flags)?;
+    let usage = DiskUsage::new(filesystem);
+    match command {
+        "tree" => print!("{}", usage.tree()),
+        "du" => {
+            let max_depth = flags.get("-d").map(|d| d.parse()).transpose()?;
+            for entry in usage.du(max_depth) {
+                println!("{}\t{}", entry.size, entry.path);
             }
-            Err(e) => Err(e.to_string()),
-        }?
-    }
-    println!("");
-
-    fn pretty_print(d: Rc<RefCell<Directory>>, indent: i32) {
-        let d = d.borrow();
-        let indent_str = (0..indent).map(|_| "  ").collect::<Vec<&str>>().join("");
-        println!("{}- {} (dir)", indent_str, d.name());
-        for child in d.child_dirs.iter() {
-            pretty_print(child.clone(), indent + 1);
         }
-        for child in d.child_files.iter() {
-            println!(
-                "{}  - {} (file, size={})",
-                indent_str,
-                child.name(),
-                child.size()
-            );
-        }
-    }
-    pretty_print(root_directory.clone(), 0);
-    println!("");
-
-    const FILESYSTEM_CAPACITY: usize = 70000000;
-    const NEEDED_SPACE: usize = 30000000;
-
-    fn find_sizes(
-        d: Rc<RefCell<Directory>>,
-        root: Rc<RefCell<Directory>>,
-        result: &mut Option<Rc<RefCell<Directory>>>,
-    ) {
-        let total_size_if_deleted = root.borrow().size() - d.borrow().size();
-        let remaining_capacity_if_deleted = FILESYSTEM_CAPACITY - total_size_if_deleted;
-        if remaining_capacity_if_deleted >= NEEDED_SPACE {
-            println!("deleting {} would do it", d.borrow().name());
-            if let Some(r) = result {
-                if d.borrow().size() < r.borrow().size() {
-                    println!(
-                        "this one {} is smaller than previous best {}, keeping this one",
-                        d.borrow().name(),
-                        r.borrow().name()
-                    );
-                    *result = Some(d.clone());
-                } else {
-                    println!(
-                        "the previous best {} is smaller than this one {}, keeping the previous best",
-                        r.borrow().name(),
-                        d.borrow().name()
-                    );
-                }
-            } else {
-                println!("no best result yet, keeping this one");
-                *result = Some(d.clone());
+        "find" => {
+            let query = Query {
+                min_size: flags.get("--min-size").map(|s| s.parse()).transpose()?,
+                max_size: flags.get("--max-size").map(|s| s.parse()).transpose()?,
+                name: flags
+                    .get("--name")
+                    .map(|s| glob::Pattern::new(s))
+                    .transpose()?,
+                kind: match flags.get("--type") {
+                    None => None,
+                    Some(&"d") => Some(Kind::Directory),
+                    Some(&"f") => Some(Kind::File),
+                    Some(kind) => Err(format!("unrecognized type: {}", kind))?,
+                },
+            };
+            for entry in usage.find(&query) {
+                println!("{}\t{}", entry.size, entry.path);
             }
         }
-        for child in d.borrow().child_dirs.iter() {
-            find_sizes(child.clone(), root.clone(), result);
+        "plan" => {
+            let capacity = flags
+                .get("--capacity")
+                .map(|s| s.parse())
+                .transpose()?
+                .unwrap_or(FILESYSTEM_CAPACITY);
+            let needed = flags
+                .get("--needed")
+                .map(|s| s.parse())
+                .transpose()?
+                .unwrap_or(NEEDED_SPACE);
+            let plan = usage.plan(capacity, needed)?;
+            for entry in plan.directories.iter() {
+                println!("{}\t{}", entry.size, entry.path);
+            }
+            println!("required = {}, freed = {}", plan.required, plan.freed);
         }
+        _ => Err(format!("unrecognized command: {}", command))?,
+    };
+    Ok(())
+}
+
+// flags come in "-name value" pairs
+fn parse_flags<'a>(args: &[&'a str]) -> Result<HashMap<&'a str, &'a str>, Box<dyn Error>> {
+    if !args.len().is_multiple_of(2) || args.iter().step_by(2).any(|flag| !flag.starts_with('-')) {
+        Err(format!("expected flag and value pairs, got {:?}", args))?
     }
-    let mut best = None;
-    find_sizes(root_directory.clone(), root_directory.clone(), &mut best);
-    let best = best.ok_or("no best result found")?;
-    println!(
-        "best directory found = {}, size = {}",
-        best.borrow().name(),
-        best.borrow().size()
-    );
-    println!("");
+    Ok(args.chunks(2).map(|pair| (pair[0], pair[1])).collect())
+}
+
+fn do_it(
+    r: &mut impl std::io::Read,
+    capacity: usize,
+    needed: usize,
+) -> Result<UsageEntry, Box<dyn Error>> {
+    solve(&Shell::replay(r, None)?.into_filesystem(), capacity, needed)
+}
 
-    Ok(best.clone())
+fn solve(
+    filesystem: &FileSystem,
+    capacity: usize,
+    needed: usize,
+) -> Result<UsageEntry, Box<dyn Error>> {
+    let usage = DiskUsage::new(filesystem);
+    println!();
+
+    print!("{}", usage.tree());
+    println!();
+
+    let required = usage.required(capacity, needed)?;
+    println!("need to free up {}", required);
+    let best = usage
+        .find(&Query {
+            min_size: Some(required),
+            kind: Some(Kind::Directory),
+            ..Default::default()
+        })
+        .into_iter()
+        .min_by_key(|entry| entry.size)
+        .ok_or("no best result found")?
+        .clone();
+    println!("best directory found = {}, size = {}", best.path, best.size);
+    println!();
+
+    Ok(best)
 }
 
 #[cfg(test)]
@@ -290,8 +192,10 @@ $ ls
 5626152 d.ext
 7214296 k"
                 .as_bytes(),
+            FILESYSTEM_CAPACITY,
+            NEEDED_SPACE,
         )?;
-        assert_eq!("d", result.borrow().name());
+        assert_eq!("d", result.name);
         Ok(())
     }
 }
@@ -0,0 +1,335 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    error::Error,
+    fmt,
+    io::{BufRead, BufReader},
+};
+
+use crate::shell::{resolve_path, Line, LineParser};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DiagnosticKind {
+    FileSizeChanged {
+        path: String,
+        previous_size: usize,
+        previous_line: usize,
+        size: usize,
+    },
+    EntryDisappeared {
+        path: String,
+        previous_line: usize,
+    },
+    // listed as a file and then as a directory, or the other way round
+    EntryTypeChanged {
+        path: String,
+        previous_line: usize,
+        directory: bool,
+    },
+    DirectoryNeverListed {
+        path: String,
+    },
+    DirectoryNeverVisited {
+        path: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub kind: DiagnosticKind,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            DiagnosticKind::FileSizeChanged {
+                path,
+                previous_size,
+                previous_line,
+                size,
+            } => write!(
+                f,
+                "file {} changed size from {} (line {}) to {}",
+                path, previous_size, previous_line, size
+            ),
+            DiagnosticKind::EntryDisappeared {
+                path,
+                previous_line,
+            } => write!(
+                f,
+                "{} was listed on line {} but is missing now",
+                path, previous_line
+            ),
+            DiagnosticKind::EntryTypeChanged {
+                path,
+                previous_line,
+                directory,
+            } => {
+                let (was, is) = if *directory {
+                    ("file", "directory")
+                } else {
+                    ("directory", "file")
+                };
+                write!(
+                    f,
+                    "{} was a {} on line {} but is a {} now",
+                    path, was, previous_line, is
+                )
+            }
+            DiagnosticKind::DirectoryNeverListed { path } => {
+                write!(f, "directory {} was entered but never listed", path)
+            }
+            DiagnosticKind::DirectoryNeverVisited { path } => {
+                write!(f, "directory {} was listed but never entered", path)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ListedEntry {
+    Directory,
+    File(usize),
+}
+
+#[derive(Debug)]
+struct Listing {
+    directory: Vec<String>,
+    line: usize,
+    // name -> (entry, line it was printed on)
+    entries: BTreeMap<String, (ListedEntry, usize)>,
+}
+
+// cross-checks every ls against the previous ls of the same directory, without building the tree
+pub fn validate(r: &mut impl std::io::Read) -> Result<Vec<Diagnostic>, Box<dyn Error>> {
+    let parser = LineParser::new()?;
+
+    let mut results = Vec::new();
+    let mut current_working_directory = Vec::<String>::new();
+    let mut current_listing: Option<Listing> = None;
+    let mut previous_listings = HashMap::<Vec<String>, Listing>::new();
+    let mut listed = HashSet::<Vec<String>>::new();
+    // first line each directory was entered or announced by a dir entry
+    let mut entered = BTreeMap::<Vec<String>, usize>::new();
+    let mut announced = BTreeMap::<Vec<String>, usize>::new();
+
+    for (i, line) in BufReader::new(r).lines().enumerate() {
+        let line_number = i + 1;
+        let line = line?;
+        let parsed = parser
+            .parse(&line)
+            .map_err(|e| format!("line {}: {}", line_number, e));
+        if let Ok(Line::Command(command, args)) = &parsed {
+            finish_listing(current_listing.take(), &mut previous_listings, &mut results);
+            match (*command, args.as_slice()) {
+                ("cd", [path]) => {
+                    current_working_directory = resolve_path(&current_working_directory, path);
+                    entered
+                        .entry(current_working_directory.clone())
+                        .or_insert(line_number);
+                }
+                ("ls", []) => {
+                    listed.insert(current_working_directory.clone());
+                    current_listing = Some(Listing {
+                        directory: current_working_directory.clone(),
+                        line: line_number,
+                        entries: BTreeMap::new(),
+                    });
+                }
+                // anything else may legitimately change what the next ls prints
+                _ => previous_listings.clear(),
+            };
+        } else if let Some(listing) = current_listing.as_mut() {
+            let (name, entry) = match parsed? {
+                Line::Directory(name) => {
+                    let mut path = listing.directory.clone();
+                    path.push(name.to_string());
+                    announced.entry(path).or_insert(line_number);
+                    (name.to_string(), ListedEntry::Directory)
+                }
+                Line::File(size, name) => (name.to_string(), ListedEntry::File(size)),
+                Line::Command(_, _) => unreachable!("commands were handled above"),
+            };
+            // a repeated name has to agree with itself, otherwise compare against the last ls
+            let earlier = match listing.entries.get(&name) {
+                Some(earlier) => Some(*earlier),
+                None => previous_listings
+                    .get(&listing.directory)
+                    .and_then(|previous| previous.entries.get(&name))
+                    .copied(),
+            };
+            match (earlier, entry) {
+                (
+                    Some((ListedEntry::File(previous_size), previous_line)),
+                    ListedEntry::File(size),
+                ) if previous_size != size => {
+                    results.push(Diagnostic {
+                        line: line_number,
+                        kind: DiagnosticKind::FileSizeChanged {
+                            path: format_path(&listing.directory, Some(&name)),
+                            previous_size,
+                            previous_line,
+                            size,
+                        },
+                    });
+                }
+                (Some((ListedEntry::File(_), previous_line)), ListedEntry::Directory)
+                | (Some((ListedEntry::Directory, previous_line)), ListedEntry::File(_)) => {
+                    results.push(Diagnostic {
+                        line: line_number,
+                        kind: DiagnosticKind::EntryTypeChanged {
+                            path: format_path(&listing.directory, Some(&name)),
+                            previous_line,
+                            directory: entry == ListedEntry::Directory,
+                        },
+                    });
+                }
+                _ => {}
+            }
+            listing.entries.entry(name).or_insert((entry, line_number));
+        } else {
+            Err(format!(
+                "line {}: output without a preceding ls: {}",
+                line_number, line
+            ))?
+        }
+    }
+    finish_listing(current_listing.take(), &mut previous_listings, &mut results);
+
+    for (path, line) in entered.iter() {
+        if !listed.contains(path) {
+            results.push(Diagnostic {
+                line: *line,
+                kind: DiagnosticKind::DirectoryNeverListed {
+                    path: format_path(path, None),
+                },
+            });
+        }
+    }
+    for (path, line) in announced.iter() {
+        if !entered.contains_key(path) {
+            results.push(Diagnostic {
+                line: *line,
+                kind: DiagnosticKind::DirectoryNeverVisited {
+                    path: format_path(path, None),
+                },
+            });
+        }
+    }
+
+    results.sort();
+    Ok(results)
+}
+
+fn finish_listing(
+    listing: Option<Listing>,
+    previous_listings: &mut HashMap<Vec<String>, Listing>,
+    results: &mut Vec<Diagnostic>,
+) {
+    if let Some(listing) = listing {
+        if let Some(previous) = previous_listings.get(&listing.directory) {
+            for (name, (_, line)) in previous.entries.iter() {
+                if !listing.entries.contains_key(name) {
+                    results.push(Diagnostic {
+                        line: listing.line,
+                        kind: DiagnosticKind::EntryDisappeared {
+                            path: format_path(&listing.directory, Some(name)),
+                            previous_line: *line,
+                        },
+                    });
+                }
+            }
+        }
+        previous_listings.insert(listing.directory.clone(), listing);
+    }
+}
+
+fn format_path(directory: &[String], name: Option<&str>) -> String {
+    let mut components = directory.to_vec();
+    components.extend(name.map(|name| name.to_string()));
+    format!("/{}", components.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consistent_transcript() -> Result<(), Box<dyn Error>> {
+        let result = validate(
+            &mut r"$ cd /
+$ ls
+dir a
+10 b.txt
+$ cd a
+$ ls
+$ cd /
+$ ls
+dir a
+10 b.txt"
+                .as_bytes(),
+        )?;
+        assert_eq!(Vec::<Diagnostic>::new(), result);
+        Ok(())
+    }
+
+    #[test]
+    fn inconsistent_transcript() -> Result<(), Box<dyn Error>> {
+        let result = validate(
+            &mut r"$ cd /
+$ ls
+dir a
+dir b
+10 c.txt
+20 d.txt
+$ cd a
+$ cd /
+$ ls
+dir a
+dir b
+15 c.txt
+15 c.txt
+30 c.txt"
+                .as_bytes(),
+        )?;
+        assert_eq!(
+            vec![
+                "line 4: directory /b was listed but never entered",
+                "line 7: directory /a was entered but never listed",
+                "line 9: /d.txt was listed on line 6 but is missing now",
+                "line 12: file /c.txt changed size from 10 (line 5) to 15",
+                "line 14: file /c.txt changed size from 15 (line 12) to 30",
+            ],
+            result.iter().map(|d| d.to_string()).collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn entry_type_changed() -> Result<(), Box<dyn Error>> {
+        let result = validate(
+            &mut r"$ cd /
+$ ls
+10 a
+dir b
+$ cd b
+$ ls
+$ cd /
+$ ls
+dir a
+5 b
+5 b"
+            .as_bytes(),
+        )?;
+        assert_eq!(
+            vec![
+                "line 9: /a was a file on line 3 but is a directory now",
+                "line 9: directory /a was listed but never entered",
+                "line 10: /b was a directory on line 4 but is a file now",
+            ],
+            result.iter().map(|d| d.to_string()).collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+}
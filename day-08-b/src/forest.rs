@@ -0,0 +1,122 @@
+use crate::{Map, Point, Size};
+
+// what's known about one tree, put together from the forest's arrays when it's asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tree {
+    // a bit for each direction where every tree between this one and that edge is shorter
+    visible_from: u8,
+    scenic_score: i32,
+}
+
+impl Tree {
+    pub fn is_visible(&self) -> bool {
+        self.visible_from != 0
+    }
+
+    // how many trees can be seen in each direction before one at least as tall, or the edge, all
+    // multiplied together
+    pub fn scenic_score(&self) -> i32 {
+        self.scenic_score
+    }
+}
+
+// every tree's view computed up front, one monotonic stack pass per row and column direction,
+// reading the map's heights in place and keeping a byte and a score per tree
+pub struct Forest {
+    size: Size,
+    visible_from: Vec<u8>,
+    scenic_scores: Vec<i32>,
+}
+
+impl Forest {
+    pub fn new(map: &Map) -> Forest {
+        let size = map.size();
+        let heights = map.heights();
+        let mut result = Forest {
+            size,
+            visible_from: vec![0; heights.len()],
+            scenic_scores: vec![1; heights.len()],
+        };
+
+        let index = |x: i32, y: i32| (x + y * size.width()) as usize;
+        for y in 0..size.height() {
+            let line = (0..size.width()).map(|x| index(x, y));
+            result.scan(heights, line.clone(), 0);
+            result.scan(heights, line.rev(), 1);
+        }
+        for x in 0..size.width() {
+            let line = (0..size.height()).map(|y| index(x, y));
+            result.scan(heights, line.clone(), 2);
+            result.scan(heights, line.rev(), 3);
+        }
+        result
+    }
+
+    pub fn get_at(&self, p: Point) -> Result<Tree, String> {
+        if p.x() < 0 || p.y() < 0 || p.x() >= self.size.width() || p.y() >= self.size.height() {
+            Err(format!("out of bounds {:?}, size = {:?}", p, self.size))
+        } else {
+            Ok(self.tree((p.x() + p.y() * self.size.width()) as usize))
+        }
+    }
+
+    // row by row, left to right
+    pub fn iter(&self) -> impl Iterator<Item = (Point, Tree)> + '_ {
+        let width = self.size.width();
+        (0..self.scenic_scores.len())
+            .map(move |i| (Point::new(i as i32 % width, i as i32 / width), self.tree(i)))
+    }
+
+    fn tree(&self, i: usize) -> Tree {
+        Tree {
+            visible_from: self.visible_from[i],
+            scenic_score: self.scenic_scores[i],
+        }
+    }
+
+    // walks a line of trees starting at the edge they're all looking towards, which is 0 to 3 for
+    // left, right, up and down
+    fn scan(&mut self, heights: &[u8], line: impl Iterator<Item = usize>, direction: usize) {
+        // positions along the line, heights never increasing from bottom to top
+        let mut stack: Vec<(usize, u8)> = Vec::new();
+        for (position, i) in line.enumerate() {
+            let height = heights[i];
+            while stack.last().is_some_and(|(_, h)| *h < height) {
+                stack.pop();
+            }
+            // the nearest tree at least as tall blocks both the view and visibility
+            let distance = match stack.last() {
+                Some((blocker, _)) => position - blocker,
+                None => {
+                    self.visible_from[i] |= 1 << direction;
+                    position
+                }
+            };
+            self.scenic_scores[i] *= distance as i32;
+            stack.push((position, height));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::loader::Loader;
+
+    #[test]
+    fn sample() -> Result<(), String> {
+        let map = Loader::default()
+            .load(&mut "30373\n25512\n65332\n33549\n35390".as_bytes())
+            .map_err(|e| e.to_string())?;
+        let forest = Forest::new(&map);
+        assert_eq!(
+            21,
+            forest.iter().filter(|(_, tree)| tree.is_visible()).count()
+        );
+        assert_eq!(8, forest.get_at(Point::new(2, 3))?.scenic_score());
+        assert!(!forest.get_at(Point::new(3, 1))?.is_visible());
+        assert!(forest.get_at(Point::new(5, 0)).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    error::Error,
+    io::{BufRead, BufReader},
+    str::FromStr,
+};
+
+use crate::{Map, Point, Size};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    // one char per tree, 0-9
+    Decimal,
+    // one char per tree, 0-9 then a-f
+    Hex,
+    // one char per tree, a is 0 up to z is 25
+    Letters,
+    // any number of digits per tree, separated by whitespace
+    Numbers,
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "decimal" => Ok(Encoding::Decimal),
+            "hex" => Ok(Encoding::Hex),
+            "letters" => Ok(Encoding::Letters),
+            "numbers" => Ok(Encoding::Numbers),
+            _ => Err(format!("unrecognized encoding: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ragged {
+    // every row has to be as long as the first one
+    Reject,
+    // short rows are filled out to the longest one with trees of this height
+    Pad(u8),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Loader {
+    pub encoding: Encoding,
+    pub ragged: Ragged,
+}
+
+impl Default for Loader {
+    fn default() -> Self {
+        Self {
+            encoding: Encoding::Decimal,
+            ragged: Ragged::Reject,
+        }
+    }
+}
+
+impl Loader {
+    pub fn load(&self, r: &mut impl std::io::Read) -> Result<Map, Box<dyn Error>> {
+        let mut rows = Vec::new();
+        for (i, line) in BufReader::new(r).lines().enumerate() {
+            let line = line?;
+            let row = self
+                .parse_row(&line)
+                .map_err(|(column, e)| format!("line {}, column {}: {}", i + 1, column, e))?;
+            rows.push(row);
+        }
+        // a trailing newline or two isn't a row of zero trees
+        while rows.last().is_some_and(|row| row.is_empty()) {
+            rows.pop();
+        }
+
+        let width = match self.ragged {
+            Ragged::Reject => {
+                let width = rows.first().map(|row| row.len()).unwrap_or(0);
+                for (i, row) in rows.iter().enumerate() {
+                    if row.len() != width {
+                        Err(format!(
+                            "line {}, column {}: expected {} trees like line 1 but found {}",
+                            i + 1,
+                            row.len().min(width) + 1,
+                            width,
+                            row.len()
+                        ))?
+                    }
+                }
+                width
+            }
+            Ragged::Pad(_) => rows.iter().map(|row| row.len()).max().unwrap_or(0),
+        };
+
+        let mut map = Map::new(Size::new(width as i32, rows.len() as i32));
+        for (y, row) in rows.iter().enumerate() {
+            for x in 0..width {
+                let value = match (row.get(x), self.ragged) {
+                    (Some(value), _) => *value,
+                    (None, Ragged::Pad(value)) => value,
+                    (None, Ragged::Reject) => unreachable!("rows were already checked"),
+                };
+                map.set_at(Point::new(x as i32, y as i32), value)?;
+            }
+        }
+        Ok(map)
+    }
+
+    // errors come with the 1-based column they were found at
+    fn parse_row(&self, line: &str) -> Result<Vec<u8>, (usize, String)> {
+        let mut results = Vec::new();
+        match self.encoding {
+            Encoding::Numbers => {
+                // the column and byte offset the current number started at
+                let mut start = None;
+                // an extra space on the end finishes the last number
+                let chars = line.char_indices().chain([(line.len(), ' ')]);
+                for (column, (offset, c)) in chars.enumerate() {
+                    match (c.is_whitespace(), start) {
+                        (false, None) => start = Some((column, offset)),
+                        (true, Some((s, from))) => {
+                            let token = &line[from..offset];
+                            let value = token
+                                .parse::<u8>()
+                                .map_err(|e| (s + 1, format!("bad height {}: {}", token, e)))?;
+                            results.push(value);
+                            start = None;
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            _ => {
+                for (column, c) in line.chars().enumerate() {
+                    let value = match self.encoding {
+                        Encoding::Decimal => c.to_digit(10),
+                        Encoding::Hex => c.to_digit(16),
+                        Encoding::Letters if c.is_ascii_lowercase() => Some(c as u32 - 'a' as u32),
+                        _ => None,
+                    }
+                    .ok_or((
+                        column + 1,
+                        format!("{:?} isn't a {:?} height", c, self.encoding),
+                    ))?;
+                    results.push(value as u8);
+                }
+            }
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(encoding: Encoding, ragged: Ragged, s: &str) -> Result<Map, Box<dyn Error>> {
+        Loader { encoding, ragged }.load(&mut s.as_bytes())
+    }
+
+    fn row(map: &Map, y: i32) -> Result<Vec<i32>, String> {
+        (0..map.size().width())
+            .map(|x| map.get_at(Point::new(x, y)))
+            .collect()
+    }
+
+    #[test]
+    fn encodings() -> Result<(), Box<dyn Error>> {
+        let map = load(Encoding::Hex, Ragged::Reject, "0aF\n123\n")?;
+        assert_eq!(vec![0, 10, 15], row(&map, 0)?);
+        let map = load(Encoding::Letters, Ragged::Reject, "abz")?;
+        assert_eq!(vec![0, 1, 25], row(&map, 0)?);
+        let map = load(Encoding::Numbers, Ragged::Reject, " 10  200 3\n1 2 255")?;
+        assert_eq!(vec![10, 200, 3], row(&map, 0)?);
+        assert_eq!(vec![1, 2, 255], row(&map, 1)?);
+        Ok(())
+    }
+
+    #[test]
+    fn ragged_rows() -> Result<(), Box<dyn Error>> {
+        assert_eq!(
+            "line 3, column 2: expected 3 trees like line 1 but found 1",
+            load(Encoding::Decimal, Ragged::Reject, "123\n456\n7")
+                .err()
+                .unwrap()
+                .to_string()
+        );
+        let map = load(Encoding::Decimal, Ragged::Pad(9), "123\n456\n7")?;
+        assert_eq!(vec![7, 9, 9], row(&map, 2)?);
+        Ok(())
+    }
+
+    #[test]
+    fn bad_heights() {
+        assert_eq!(
+            "line 2, column 3: 'x' isn't a Decimal height",
+            load(Encoding::Decimal, Ragged::Reject, "123\n45x")
+                .err()
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "line 1, column 4: bad height 256: number too large to fit in target type",
+            load(Encoding::Numbers, Ragged::Reject, "1  256")
+                .err()
+                .unwrap()
+                .to_string()
+        );
+        // columns count characters, not bytes
+        assert_eq!(
+            "line 1, column 4: bad height 2é: invalid digit found in string",
+            load(Encoding::Numbers, Ragged::Reject, "\u{a0}1 2é 3")
+                .err()
+                .unwrap()
+                .to_string()
+        );
+    }
+}
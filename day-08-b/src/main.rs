@@ -1,11 +1,20 @@
+mod forest;
+mod loader;
+mod viewshed;
+
 use std::{
-    cmp::{max, min},
+    cmp::max,
+    env,
     error::Error,
     fmt,
-    io::{self, BufRead, BufReader},
+    io::{self, BufReader},
 };
 
-#[derive(Copy, Clone)]
+use forest::Forest;
+use loader::{Loader, Ragged};
+use viewshed::Rays;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
 struct Point {
     x: i32,
     y: i32,
@@ -59,14 +68,15 @@ impl fmt::Debug for Size {
 
 struct Map {
     size: Size,
-    data: Vec<i32>,
+    // heights fit in a byte, which keeps big forests small
+    data: Vec<u8>,
 }
 
 impl Map {
-    fn new() -> Map {
+    fn new(size: Size) -> Map {
         Map {
-            size: Size::new(0, 0),
-            data: Vec::new(),
+            size,
+            data: vec![0; (size.width() * size.height()) as usize],
         }
     }
 
@@ -74,16 +84,21 @@ impl Map {
         self.size
     }
 
+    // row by row, left to right
+    fn heights(&self) -> &[u8] {
+        &self.data
+    }
+
     fn get_at(&self, p: Point) -> Result<i32, String> {
         let size = self.size();
         if p.x() < 0 || p.y() < 0 || p.x() >= size.width() || p.y() >= size.height() {
-            Err(format!("out of bounds {:?}, size = {:?}", p, self))
+            Err(format!("out of bounds {:?}, size = {:?}", p, self.size))
         } else {
-            Ok(self.data[(p.x() + p.y() * size.width()) as usize])
+            Ok(self.data[(p.x() + p.y() * size.width()) as usize] as i32)
         }
     }
 
-    fn set_at(&mut self, p: Point, value: i32) -> Result<(), String> {
+    fn set_at(&mut self, p: Point, value: u8) -> Result<(), String> {
         let size = self.size();
         if p.x() < 0 || p.y() < 0 || p.x() >= size.width() || p.y() >= size.height() {
             Err(format!("out of bounds {:?}, size = {:?}", p, self.size))
@@ -92,73 +107,24 @@ impl Map {
             Ok(())
         }
     }
-
-    fn set_size(&mut self, size: Size) -> Result<(), String> {
-        if size.width() < 0 || size.height() < 0 {
-            Err(format!("size must be positive {:?}", size))?
-        }
-        if size.width() == 0 || size.height() == 0 {
-            self.size = Size::new(0, 0);
-            self.data.clear();
-        } else {
-            let mut new_data = Vec::new();
-            let copy_width = min(self.size().width(), size.width());
-            let extra_width = if size.width() > copy_width {
-                size.width() - copy_width
-            } else {
-                0
-            };
-            let copy_height = min(self.size().height(), size.height());
-            let extra_height = if size.height() > copy_height {
-                size.height() - copy_height
-            } else {
-                0
-            };
-            for y in 0..copy_height {
-                for x in 0..copy_width {
-                    new_data.push(self.get_at(Point::new(x, y))?);
-                }
-                for _ in 0..extra_width {
-                    new_data.push(0);
-                }
-            }
-            for _ in 0..extra_height {
-                for _ in 0..size.width() {
-                    new_data.push(0);
-                }
-            }
-            self.size = size;
-            self.data = new_data;
-        }
-        Ok(())
-    }
-
-    fn grow(&mut self, size: Size) -> Result<(), String> {
-        let desired_width = max(self.size().width(), size.width());
-        let desired_height = max(self.size().height(), size.height());
-        if desired_width > self.size().width() || desired_height > self.size().height() {
-            self.set_size(Size::new(desired_width, desired_height))?;
-        }
-        Ok(())
-    }
-
-    fn set_and_grow(&mut self, p: Point, value: i32) -> Result<(), String> {
-        self.grow(Size::new(p.x() + 1, p.y() + 1))?;
-        self.set_at(p, value)?;
-        Ok(())
-    }
 }
 
 impl fmt::Debug for Map {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // multi-digit heights need something between them
+        let separator = if self.data.iter().all(|x| *x < 10) {
+            ""
+        } else {
+            " "
+        };
         let data = self
             .data
-            .chunks(self.size().width() as usize)
+            .chunks(self.size().width().max(1) as usize)
             .map(|row| {
                 row.iter()
                     .map(|x| x.to_string())
                     .collect::<Vec<String>>()
-                    .join("")
+                    .join(separator)
             })
             .collect::<Vec<String>>()
             .join("\n");
@@ -168,39 +134,125 @@ impl fmt::Debug for Map {
 
 fn main() -> Result<(), Box<dyn Error>> {
     let mut r = BufReader::new(io::stdin());
-    do_it(&mut r)?;
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+    let (loader, args) = parse_loader(&args)?;
+    match args {
+        [] => {
+            do_it(&mut r, &loader)?;
+        }
+        // compares the fast analysis against checking every tree the slow way
+        ["check"] => {
+            let map = loader.load(&mut r)?;
+            let mismatches = check(&map, &Forest::new(&map))?;
+            for p in mismatches.iter() {
+                println!("mismatch at {:?}", p);
+            }
+            println!("{} mismatches", mismatches.len());
+        }
+        // what can be seen from one tree, looking from eye height above its top
+        ["viewshed", x, y, eye_height, rays @ ..] => {
+            let map = loader.load(&mut r)?;
+            let rays = match rays {
+                [] | ["compass"] => Rays::Compass,
+                ["perimeter"] => Rays::Perimeter,
+                targets => Rays::Towards(
+                    targets
+                        .iter()
+                        .map(|target| parse_point(target))
+                        .collect::<Result<_, _>>()?,
+                ),
+            };
+            let viewshed = map.viewshed(
+                parse_point(&format!("{},{}", x, y))?,
+                eye_height.parse()?,
+                &rays,
+            )?;
+            print!("{}", viewshed.overlay(&map)?);
+            println!("{} trees visible", viewshed.count());
+        }
+        // the tree to put a treehouse in to see the most other trees
+        ["site", eye_height] => {
+            let map = loader.load(&mut r)?;
+            let eye_height = eye_height.parse()?;
+            let mut best: Option<(usize, Point)> = None;
+            for y in 0..map.size().height() {
+                for x in 0..map.size().width() {
+                    let p = Point::new(x, y);
+                    let count = map.viewshed(p, eye_height, &Rays::Compass)?.count();
+                    if best.is_none_or(|(best_count, _)| count > best_count) {
+                        best = Some((count, p));
+                    }
+                }
+            }
+            let (count, p) = best.ok_or("empty map")?;
+            print!(
+                "{}",
+                map.viewshed(p, eye_height, &Rays::Compass)?.overlay(&map)?
+            );
+            println!("best site = {:?}, {} trees visible", p, count);
+        }
+        _ => Err(format!("unrecognized arguments: {:?}", args))?,
+    };
     Ok(())
 }
 
-fn do_it(r: &mut impl std::io::Read) -> Result<i32, Box<dyn Error>> {
-    let mut map = Map::new();
-    let mut y = 0;
-    for line in BufReader::new(r).lines() {
-        let line = line?;
-        let mut x = 0;
-        for c in line.chars() {
-            let value = c
-                .to_digit(10)
-                .ok_or(format!("not a digit at {}, {}, c = {}", x, y, c))?
-                as i32;
-            map.set_and_grow(Point::new(x, y), value)?;
-            x += 1;
+// how to read the forest comes from any flags before the command
+fn parse_loader<'a, 'b>(args: &'b [&'a str]) -> Result<(Loader, &'b [&'a str]), Box<dyn Error>> {
+    let mut result = Loader::default();
+    let mut args = args;
+    loop {
+        match args {
+            ["--encoding", encoding, rest @ ..] => {
+                result.encoding = encoding.parse()?;
+                args = rest;
+            }
+            ["--pad", height, rest @ ..] => {
+                result.ragged = Ragged::Pad(height.parse()?);
+                args = rest;
+            }
+            _ => return Ok((result, args)),
         }
-        y += 1;
     }
+}
+
+fn parse_point(s: &str) -> Result<Point, Box<dyn Error>> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or(format!("expected x,y but got {}", s))?;
+    Ok(Point::new(x.trim().parse()?, y.trim().parse()?))
+}
+
+fn do_it(r: &mut impl std::io::Read, loader: &Loader) -> Result<i32, Box<dyn Error>> {
+    let map = loader.load(r)?;
     println!("map = {:?}", map);
 
-    let mut best = 0;
+    let forest = Forest::new(&map);
+    let best = forest.iter().fold(0, |best, (p, tree)| {
+        println!(
+            "{:?} visible score = {}, is visible? {}",
+            p,
+            tree.scenic_score(),
+            tree.is_visible()
+        );
+        max(best, tree.scenic_score())
+    });
+    println!("best = {}", best);
+    Ok(best)
+}
+
+// every point where the analysis disagrees with the brute force answer
+fn check(map: &Map, forest: &Forest) -> Result<Vec<Point>, Box<dyn Error>> {
+    let mut results = Vec::new();
     for y in 0..map.size().height() {
         for x in 0..map.size().width() {
             let p = Point::new(x, y);
-            let count = visible_score(&map, p)?;
-            println!("{:?} visible score = {}", p, count);
-            best = max(best, count);
+            if forest.get_at(p)?.scenic_score() != visible_score(map, p)? {
+                results.push(p);
+            }
         }
     }
-    println!("best = {}", best);
-    Ok(best)
+    Ok(results)
 }
 
 fn visible_score(map: &Map, p: Point) -> Result<i32, Box<dyn Error>> {
@@ -273,8 +325,24 @@ mod tests {
 33549
 35390"
                     .as_bytes(),
+                &Loader::default(),
             )?
         );
         Ok(())
     }
+
+    #[test]
+    fn matches_brute_force() -> Result<(), Box<dyn Error>> {
+        // a pseudo-random forest with plenty of equal heights
+        let mut map = Map::new(Size::new(41, 37));
+        let mut seed = 12345u32;
+        for y in 0..37 {
+            for x in 0..41 {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                map.set_at(Point::new(x, y), ((seed >> 16) % 10) as u8)?;
+            }
+        }
+        assert!(check(&map, &Forest::new(&map))?.is_empty());
+        Ok(())
+    }
 }
@@ -0,0 +1,244 @@
+use crate::{Map, Point, Size};
+
+// which lines of sight to follow out from the observer
+pub enum Rays {
+    // straight out along the 8 compass directions
+    Compass,
+    // a Bresenham line from the observer to each target
+    Towards(Vec<Point>),
+    // a Bresenham line to every tree on the edge of the map, which covers the whole map
+    Perimeter,
+}
+
+pub struct Viewshed {
+    size: Size,
+    observer: Point,
+    visible: Vec<bool>,
+}
+
+impl Viewshed {
+    pub fn is_visible(&self, p: Point) -> bool {
+        contains(self.size, p) && self.visible[(p.x() + p.y() * self.size.width()) as usize]
+    }
+
+    pub fn count(&self) -> usize {
+        self.visible.iter().filter(|v| **v).count()
+    }
+
+    // the map with the observer as @ and every tree that can't be seen as .
+    pub fn overlay(&self, map: &Map) -> Result<String, String> {
+        let mut result = String::new();
+        for y in 0..self.size.height() {
+            for x in 0..self.size.width() {
+                let p = Point::new(x, y);
+                result.push(if p == self.observer {
+                    '@'
+                } else if self.is_visible(p) {
+                    char::from_digit(map.get_at(p)? as u32, 36).unwrap_or('#')
+                } else {
+                    '.'
+                });
+            }
+            result.push('\n');
+        }
+        Ok(result)
+    }
+}
+
+impl Map {
+    // eye_height is how far above the top of the observer's own tree they're looking from
+    pub fn viewshed(
+        &self,
+        observer: Point,
+        eye_height: i32,
+        rays: &Rays,
+    ) -> Result<Viewshed, String> {
+        let size = self.size();
+        if !contains(size, observer) {
+            Err(format!(
+                "observer {:?} is outside the map {:?}",
+                observer, size
+            ))?
+        }
+        let eye = self.get_at(observer)? + eye_height;
+        let mut result = Viewshed {
+            size,
+            observer,
+            visible: vec![false; (size.width() * size.height()) as usize],
+        };
+
+        let lines = match rays {
+            Rays::Compass => [
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ]
+            .iter()
+            .map(|(dx, dy)| {
+                (1..)
+                    .map(|i| Point::new(observer.x() + dx * i, observer.y() + dy * i))
+                    .take_while(|p| contains(size, *p))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>(),
+            Rays::Towards(targets) => {
+                for target in targets.iter() {
+                    if !contains(size, *target) {
+                        Err(format!("target {:?} is outside the map {:?}", target, size))?
+                    }
+                }
+                targets
+                    .iter()
+                    .map(|target| line(observer, *target).into_iter().skip(1).collect())
+                    .collect()
+            }
+            Rays::Perimeter => perimeter(size)
+                .map(|target| line(observer, target).into_iter().skip(1).collect())
+                .collect(),
+        };
+
+        // a tree shows when its top rises above the line of sight to every nearer tree top
+        for points in lines.iter() {
+            let mut highest_slope = f64::NEG_INFINITY;
+            for p in points.iter() {
+                let distance =
+                    (((p.x() - observer.x()).pow(2) + (p.y() - observer.y()).pow(2)) as f64).sqrt();
+                let slope = (self.get_at(*p)? - eye) as f64 / distance;
+                if slope > highest_slope {
+                    result.visible[(p.x() + p.y() * size.width()) as usize] = true;
+                    highest_slope = slope;
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+fn contains(size: Size, p: Point) -> bool {
+    p.x() >= 0 && p.y() >= 0 && p.x() < size.width() && p.y() < size.height()
+}
+
+// every point on the border, clockwise from the top left
+fn perimeter(size: Size) -> impl Iterator<Item = Point> {
+    let (w, h) = (size.width(), size.height());
+    let top = (0..w).map(|x| Point::new(x, 0));
+    let right = (1..h).map(move |y| Point::new(w - 1, y));
+    let bottom = (0..w - 1).rev().map(move |x| Point::new(x, h - 1));
+    let left = (1..h - 1).rev().map(|y| Point::new(0, y));
+    top.chain(right).chain(bottom).chain(left)
+}
+
+// Bresenham's line, both ends included
+fn line(from: Point, to: Point) -> Vec<Point> {
+    let dx = (to.x() - from.x()).abs();
+    let dy = -(to.y() - from.y()).abs();
+    let sx = (to.x() - from.x()).signum();
+    let sy = (to.y() - from.y()).signum();
+    let mut error = dx + dy;
+    let (mut x, mut y) = (from.x(), from.y());
+    let mut results = vec![from];
+    while x != to.x() || y != to.y() {
+        let e2 = 2 * error;
+        if e2 >= dy {
+            error += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            error += dx;
+            y += sy;
+        }
+        results.push(Point::new(x, y));
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::loader::Loader;
+
+    #[test]
+    fn bresenham() {
+        let points = |a: (i32, i32), b: (i32, i32)| {
+            line(Point::new(a.0, a.1), Point::new(b.0, b.1))
+                .iter()
+                .map(|p| (p.x(), p.y()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)],
+            points((0, 0), (4, 2))
+        );
+        assert_eq!(vec![(2, 3), (1, 2), (1, 1), (0, 0)], points((2, 3), (0, 0)));
+        // steep
+        assert_eq!(
+            vec![(0, 0), (0, 1), (1, 2), (1, 3), (1, 4)],
+            points((0, 0), (1, 4))
+        );
+        assert_eq!(
+            vec![(1, 4), (1, 3), (0, 2), (0, 1), (0, 0)],
+            points((1, 4), (0, 0))
+        );
+        // negative slopes
+        assert_eq!(
+            vec![(3, 0), (2, -1), (1, -1), (0, -2)],
+            points((3, 0), (0, -2))
+        );
+        assert_eq!(
+            vec![(0, -2), (1, -1), (2, -1), (3, 0)],
+            points((0, -2), (3, 0))
+        );
+    }
+
+    #[test]
+    fn tall_trees_hide_shorter_ones() -> Result<(), Box<dyn std::error::Error>> {
+        let map = Loader::default().load(&mut "0009001".as_bytes())?;
+        let viewshed = map.viewshed(Point::new(0, 0), 0, &Rays::Compass)?;
+        assert_eq!("@0.9...\n", viewshed.overlay(&map)?);
+        // from high enough up the line of sight clears the tall tree
+        let viewshed = map.viewshed(Point::new(0, 0), 20, &Rays::Compass)?;
+        assert_eq!("@009..1\n", viewshed.overlay(&map)?);
+        let viewshed = map.viewshed(Point::new(0, 0), 40, &Rays::Compass)?;
+        assert_eq!("@009001\n", viewshed.overlay(&map)?);
+        Ok(())
+    }
+
+    #[test]
+    fn rays() -> Result<(), Box<dyn std::error::Error>> {
+        let map = Loader::default().load(
+            &mut r"30373
+25512
+65332
+33549
+35390"
+                .as_bytes(),
+        )?;
+        let observer = Point::new(2, 2);
+        let compass = map.viewshed(observer, 1, &Rays::Compass)?;
+        assert_eq!(
+            r"....3
+.551.
+.5@3.
+.354.
+3....
+",
+            compass.overlay(&map)?
+        );
+        let towards = map.viewshed(observer, 1, &Rays::Towards(vec![Point::new(0, 1)]))?;
+        assert_eq!(1, towards.count());
+        assert!(towards.is_visible(Point::new(1, 1)));
+        assert!(!towards.is_visible(Point::new(0, 1)));
+        let perimeter = map.viewshed(observer, 1, &Rays::Perimeter)?;
+        assert!(perimeter.count() > compass.count());
+        assert!(map
+            .viewshed(observer, 1, &Rays::Towards(vec![Point::new(5, 0)]))
+            .is_err());
+        Ok(())
+    }
+}
@@ -1,98 +1,121 @@
+mod rope;
+mod trail;
+
 use std::{
-    cmp::{max, min},
-    collections::HashSet,
+    env,
     error::Error,
-    fmt,
-    io::{self, BufRead, BufReader},
+    io::{self, BufReader},
 };
 
-#[derive(Debug)]
-enum Direction {
-    UP,
-    DOWN,
-    LEFT,
-    RIGHT,
-}
+use rope::{parse_moves, Rope};
 
-#[derive(Copy, Clone, PartialEq, Hash, Eq, Debug)]
-struct Point {
-    x: i32,
-    y: i32,
-}
+const KNOTS: usize = 2;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let mut r = BufReader::new(io::stdin());
-    do_it(&mut r)?;
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+    let (knots, slack, args) = parse_rope(&args)?;
+    match args {
+        [] => {
+            do_it(&mut r, knots, slack)?;
+        }
+        ["stats"] => {
+            let rope = simulate(&mut r, knots, slack)?;
+            for i in 0..rope.knots().len() {
+                let stats = trail::stats(rope.trail(i)?);
+                println!(
+                    "knot {}: {} cells, {} revisits, bounds {},{} to {},{}, longest run {}",
+                    i,
+                    stats.unique,
+                    stats.revisits,
+                    stats.min.x,
+                    stats.min.y,
+                    stats.max.x,
+                    stats.max.y,
+                    stats.longest_run
+                );
+            }
+        }
+        ["render", indices @ ..] => {
+            let rope = simulate(&mut r, knots, slack)?;
+            print!(
+                "{}",
+                trail::render_ascii(&rope, &parse_indices(indices, knots)?)?
+            );
+        }
+        ["image", path, scale, indices @ ..] => {
+            let rope = simulate(&mut r, knots, slack)?;
+            trail::render_image(&rope, &parse_indices(indices, knots)?, scale.parse()?)?
+                .save(path)?;
+        }
+        // every knot's position after each step of the head, for replaying elsewhere
+        ["dump"] => {
+            let mut rope = Rope::new(knots, slack)?;
+            println!("knots {}", knots);
+            let mut step = 0;
+            println!("{}", trail::frame(step, rope.knots()));
+            for m in parse_moves(&mut r)?.iter() {
+                for _ in 0..m.distance {
+                    rope.step(m.direction);
+                    step += 1;
+                    println!("{}", trail::frame(step, rope.knots()));
+                }
+            }
+        }
+        _ => Err(format!("unrecognized arguments: {:?}", args))?,
+    };
     Ok(())
 }
 
-fn do_it(r: &mut impl std::io::Read) -> Result<usize, Box<dyn Error>> {
-    let line_re = regex::Regex::new("^([UDLR]) ([0-9]+)$")?;
-
-    let mut head_position = Point { x: 0, y: 0 };
-    let mut tail_positions = vec![head_position];
-
-    for line in BufReader::new(r).lines() {
-        let line = line?;
-        let c = line_re
-            .captures(line.as_str())
-            .ok_or(format!("unhandled line: {}", line))?;
-        let direction = match &c[1] {
-            "U" => Direction::UP,
-            "D" => Direction::DOWN,
-            "L" => Direction::LEFT,
-            // "R"
-            _ => Direction::RIGHT,
-        };
-        let distance = c[2].parse::<i32>()?;
-        println!("move direction={:?}, distance={}", direction, distance);
+// any --knots and --slack flags before the command
+fn parse_rope<'a, 'b>(args: &'b [&'a str]) -> Result<(usize, i32, &'b [&'a str]), Box<dyn Error>> {
+    let mut knots = KNOTS;
+    let mut slack = 1;
+    let mut args = args;
+    loop {
+        match args {
+            ["--knots", value, rest @ ..] => {
+                knots = value.parse()?;
+                args = rest;
+            }
+            ["--slack", value, rest @ ..] => {
+                slack = value.parse()?;
+                args = rest;
+            }
+            _ => return Ok((knots, slack, args)),
+        }
+    }
+}
 
-        for _ in 0..distance {
-            let delta = match direction {
-                Direction::UP => Point { x: 0, y: -1 },
-                Direction::DOWN => Point { x: 0, y: 1 },
-                Direction::LEFT => Point { x: -1, y: 0 },
-                Direction::RIGHT => Point { x: 1, y: 0 },
-            };
-            head_position.x += delta.x;
-            head_position.y += delta.y;
-            println!("new head = {:?}", head_position);
+// the head and the tail of a rope this long when none are given
+fn parse_indices(args: &[&str], knots: usize) -> Result<Vec<usize>, Box<dyn Error>> {
+    if args.is_empty() {
+        let mut indices = vec![0, knots - 1];
+        // a rope of one knot is its own tail
+        indices.dedup();
+        Ok(indices)
+    } else {
+        Ok(args
+            .iter()
+            .map(|arg| arg.parse())
+            .collect::<Result<_, _>>()?)
+    }
+}
 
-            let current_tail = *tail_positions.last().unwrap();
-            let diff = Point {
-                x: head_position.x - current_tail.x,
-                y: head_position.y - current_tail.y,
-            };
-            let touching = diff.x.abs() <= 1 && diff.y.abs() <= 1;
-            let tail_delta = if touching {
-                Point { x: 0, y: 0 }
-            } else if diff.x == 0 && diff.y < 0 {
-                Point { x: 0, y: -1 }
-            } else if diff.x == 0 && diff.y > 0 {
-                Point { x: 0, y: 1 }
-            } else if diff.x < 0 && diff.y == 0 {
-                Point { x: -1, y: 0 }
-            } else if diff.x > 0 && diff.y == 0 {
-                Point { x: 1, y: 0 }
-            } else if diff.x < 0 && diff.y < 0 {
-                Point { x: -1, y: -1 }
-            } else if diff.x > 0 && diff.y < 0 {
-                Point { x: 1, y: -1 }
-            } else if diff.x < 0 && diff.y > 0 {
-                Point { x: -1, y: 1 }
-            } else {
-                // if diff.x > 0 && diff.y > 0
-                Point { x: 1, y: 1 }
-            };
-            tail_positions.push(Point {
-                x: current_tail.x + tail_delta.x,
-                y: current_tail.y + tail_delta.y,
-            });
-            println!("new tail = {:?}", tail_positions.last().unwrap());
-        }
+fn simulate(r: &mut impl std::io::Read, knots: usize, slack: i32) -> Result<Rope, Box<dyn Error>> {
+    let mut rope = Rope::new(knots, slack)?;
+    for m in parse_moves(r)?.iter() {
+        println!("move direction={:?}, distance={}", m.direction, m.distance);
+        rope.apply(m);
+        println!("new points = {:?}", rope.knots());
     }
+    Ok(rope)
+}
 
-    let result = tail_positions.iter().collect::<HashSet<_>>().len();
+fn do_it(r: &mut impl std::io::Read, knots: usize, slack: i32) -> Result<usize, Box<dyn Error>> {
+    let rope = simulate(r, knots, slack)?;
+    let result = rope.visited(knots - 1)?.len();
     println!("result = {}", result);
     Ok(result)
 }
@@ -115,8 +138,23 @@ D 1
 L 5
 R 2"
                 .as_bytes(),
+                KNOTS,
+                1,
             )?
         );
         Ok(())
     }
+
+    #[test]
+    fn render_shorter_rope() -> Result<(), Box<dyn Error>> {
+        let moves = "R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2";
+        let rope = simulate(&mut moves.as_bytes(), 1, 1)?;
+        let indices = parse_indices(&[], 1)?;
+        assert_eq!(vec![0], indices);
+        assert_eq!(
+            ".HHHH.\n.HHHHH\nHHHHHH\n....H.\nsHHHH.\n",
+            trail::render_ascii(&rope, &indices)?
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,218 @@
+use std::{
+    collections::HashSet,
+    error::Error,
+    io::{BufRead, BufReader},
+};
+
+#[derive(Copy, Clone, PartialEq, Hash, Eq, Debug)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+    fn delta(self) -> Point {
+        let (x, y) = match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+            Direction::UpLeft => (-1, -1),
+            Direction::UpRight => (1, -1),
+            Direction::DownLeft => (-1, 1),
+            Direction::DownRight => (1, 1),
+        };
+        Point { x, y }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub direction: Direction,
+    pub distance: i32,
+}
+
+pub fn parse_moves(r: &mut impl std::io::Read) -> Result<Vec<Move>, Box<dyn Error>> {
+    let line_re = regex::Regex::new("^(U|D|L|R|UL|UR|DL|DR) ([0-9]+)$")?;
+    let mut results = Vec::new();
+    for (i, line) in BufReader::new(r).lines().enumerate() {
+        let line = line?;
+        let c = line_re.captures(line.as_str()).ok_or(format!(
+            "line {}: unhandled line: {}",
+            i + 1,
+            line
+        ))?;
+        let direction = match &c[1] {
+            "U" => Direction::Up,
+            "D" => Direction::Down,
+            "L" => Direction::Left,
+            "R" => Direction::Right,
+            "UL" => Direction::UpLeft,
+            "UR" => Direction::UpRight,
+            "DL" => Direction::DownLeft,
+            // "DR"
+            _ => Direction::DownRight,
+        };
+        let distance = c[2].parse::<i32>()?;
+        results.push(Move {
+            direction,
+            distance,
+        });
+    }
+    Ok(results)
+}
+
+pub struct Rope {
+    // the head is first
+    knots: Vec<Point>,
+    // how far apart two neighbouring knots can get before the follower has to move
+    slack: i32,
+    visited: Vec<HashSet<Point>>,
+    // every position each knot has been in, in order, starting with the origin
+    trails: Vec<Vec<Point>>,
+}
+
+impl Rope {
+    pub fn new(knots: usize, slack: i32) -> Result<Rope, String> {
+        if knots == 0 || slack < 1 {
+            Err(format!(
+                "need at least one knot and a slack of at least 1, knots = {}, slack = {}",
+                knots, slack
+            ))?
+        }
+        let start = Point { x: 0, y: 0 };
+        Ok(Rope {
+            knots: vec![start; knots],
+            slack,
+            visited: vec![HashSet::from([start]); knots],
+            trails: vec![vec![start]; knots],
+        })
+    }
+
+    pub fn knots(&self) -> &[Point] {
+        &self.knots
+    }
+
+    // every cell knot i has been in, the head is 0
+    pub fn visited(&self, i: usize) -> Result<&HashSet<Point>, String> {
+        self.visited.get(i).ok_or(format!(
+            "no knot {}, the rope only has {}",
+            i,
+            self.knots.len()
+        ))
+    }
+
+    pub fn trail(&self, i: usize) -> Result<&[Point], String> {
+        self.trails
+            .get(i)
+            .map(|trail| trail.as_slice())
+            .ok_or(format!(
+                "no knot {}, the rope only has {}",
+                i,
+                self.knots.len()
+            ))
+    }
+
+    pub fn apply(&mut self, m: &Move) {
+        for _ in 0..m.distance {
+            self.step(m.direction);
+        }
+    }
+
+    // moves the head one cell and lets the rest of the rope catch up
+    pub fn step(&mut self, direction: Direction) {
+        let delta = direction.delta();
+        self.knots[0].x += delta.x;
+        self.knots[0].y += delta.y;
+        self.visited[0].insert(self.knots[0]);
+        self.trails[0].push(self.knots[0]);
+        for i in 1..self.knots.len() {
+            let target = self.knots[i - 1];
+            let point = &mut self.knots[i];
+            let diff = Point {
+                x: target.x - point.x,
+                y: target.y - point.y,
+            };
+            // once a link is stretched too far the follower takes one step, diagonally if need be
+            if diff.x.abs() <= self.slack && diff.y.abs() <= self.slack {
+                break;
+            }
+            point.x += diff.x.signum();
+            point.y += diff.y.signum();
+            self.visited[i].insert(*point);
+            self.trails[i].push(*point);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(knots: usize, slack: i32, moves: &str) -> Result<Rope, Box<dyn Error>> {
+        let mut rope = Rope::new(knots, slack)?;
+        for m in parse_moves(&mut moves.as_bytes())?.iter() {
+            rope.apply(m);
+        }
+        Ok(rope)
+    }
+
+    #[test]
+    fn every_knot_in_one_pass() -> Result<(), Box<dyn Error>> {
+        let rope = run(10, 1, "R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2")?;
+        assert_eq!(13, rope.visited(1)?.len());
+        assert_eq!(1, rope.visited(9)?.len());
+        assert!(rope.visited(10).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn diagonal_moves() -> Result<(), Box<dyn Error>> {
+        let rope = run(3, 1, "UR 3\nDL 1")?;
+        assert_eq!(
+            vec![
+                Point { x: 2, y: -2 },
+                Point { x: 2, y: -2 },
+                Point { x: 1, y: -1 }
+            ],
+            rope.knots()
+        );
+        assert_eq!(4, rope.visited(0)?.len());
+        assert_eq!(2, rope.visited(2)?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn slack() -> Result<(), Box<dyn Error>> {
+        let rope = run(2, 3, "R 3")?;
+        assert_eq!(Point { x: 0, y: 0 }, rope.knots()[1]);
+        let rope = run(2, 3, "R 5\nU 1")?;
+        assert_eq!(Point { x: 2, y: 0 }, rope.knots()[1]);
+        assert_eq!(3, rope.visited(1)?.len());
+        assert!(Rope::new(2, 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bad_line() {
+        assert_eq!(
+            "line 2: unhandled line: X 1",
+            parse_moves(&mut "R 1\nX 1".as_bytes())
+                .err()
+                .unwrap()
+                .to_string()
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use std::{collections::HashSet, fmt::Write};
+
+use crate::rope::{Point, Rope};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KnotStats {
+    pub unique: usize,
+    // moves that landed on a cell the knot had already been in
+    pub revisits: usize,
+    pub min: Point,
+    pub max: Point,
+    // the most moves in a row in the same direction
+    pub longest_run: usize,
+}
+
+pub fn stats(trail: &[Point]) -> KnotStats {
+    let unique = trail.iter().collect::<HashSet<_>>().len();
+    let (min, max) = bounds(trail.iter());
+    let mut longest_run = 0;
+    let mut run = 0;
+    let mut last_delta = None;
+    for pair in trail.windows(2) {
+        let delta = (pair[1].x - pair[0].x, pair[1].y - pair[0].y);
+        run = if last_delta == Some(delta) {
+            run + 1
+        } else {
+            1
+        };
+        longest_run = longest_run.max(run);
+        last_delta = Some(delta);
+    }
+    KnotStats {
+        unique,
+        revisits: trail.len() - unique,
+        min,
+        max,
+        longest_run,
+    }
+}
+
+// the cells the given knots visited, each drawn over the ones before it, with the start as s
+pub fn render_ascii(rope: &Rope, knots: &[usize]) -> Result<String, String> {
+    let cells = cells(rope, knots)?;
+    let (min, max) = bounds(cells.iter().map(|(p, _)| p));
+    let mut grid = vec![vec!['.'; (max.x - min.x + 1) as usize]; (max.y - min.y + 1) as usize];
+    for (p, knot) in cells.iter() {
+        grid[(p.y - min.y) as usize][(p.x - min.x) as usize] = match knot {
+            0 => 'H',
+            _ => char::from_digit((*knot % 36) as u32, 36).unwrap_or('#'),
+        };
+    }
+    grid[(-min.y) as usize][(-min.x) as usize] = 's';
+    Ok(grid
+        .iter()
+        .map(|row| row.iter().collect::<String>() + "\n")
+        .collect())
+}
+
+// the same picture as render_ascii with each cell as a square of scale pixels
+pub fn render_image(rope: &Rope, knots: &[usize], scale: u32) -> Result<image::RgbImage, String> {
+    const COLORS: [[u8; 3]; 6] = [
+        [255, 64, 64],
+        [64, 192, 64],
+        [64, 128, 255],
+        [255, 192, 0],
+        [192, 64, 255],
+        [0, 192, 192],
+    ];
+    if scale == 0 {
+        Err("scale must be positive")?
+    }
+    let cells = cells(rope, knots)?;
+    let (min, max) = bounds(cells.iter().map(|(p, _)| p));
+    let mut result = image::RgbImage::new(
+        (max.x - min.x + 1) as u32 * scale,
+        (max.y - min.y + 1) as u32 * scale,
+    );
+    let mut fill = |p: Point, color: [u8; 3]| {
+        for y in 0..scale {
+            for x in 0..scale {
+                result.put_pixel(
+                    (p.x - min.x) as u32 * scale + x,
+                    (p.y - min.y) as u32 * scale + y,
+                    image::Rgb(color),
+                );
+            }
+        }
+    };
+    for (p, knot) in cells.iter() {
+        fill(*p, COLORS[*knot % COLORS.len()]);
+    }
+    fill(Point { x: 0, y: 0 }, [255, 255, 255]);
+    Ok(result)
+}
+
+// one line per frame, the step number then every knot's position from the head back
+pub fn frame(step: usize, knots: &[Point]) -> String {
+    let mut result = step.to_string();
+    for p in knots.iter() {
+        write!(result, " {},{}", p.x, p.y).unwrap();
+    }
+    result
+}
+
+fn cells(rope: &Rope, knots: &[usize]) -> Result<Vec<(Point, usize)>, String> {
+    let mut results = Vec::new();
+    for knot in knots.iter() {
+        for p in rope.visited(*knot)?.iter() {
+            results.push((*p, *knot));
+        }
+    }
+    Ok(results)
+}
+
+// the origin is always included since every knot starts there
+fn bounds<'a>(points: impl Iterator<Item = &'a Point>) -> (Point, Point) {
+    points.fold(
+        (Point { x: 0, y: 0 }, Point { x: 0, y: 0 }),
+        |(min, max), p| {
+            (
+                Point {
+                    x: min.x.min(p.x),
+                    y: min.y.min(p.y),
+                },
+                Point {
+                    x: max.x.max(p.x),
+                    y: max.y.max(p.y),
+                },
+            )
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::rope::parse_moves;
+
+    fn sample() -> Result<Rope, Box<dyn std::error::Error>> {
+        let mut rope = Rope::new(2, 1)?;
+        for m in parse_moves(&mut "R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2".as_bytes())?.iter() {
+            rope.apply(m);
+        }
+        Ok(rope)
+    }
+
+    #[test]
+    fn knot_stats() -> Result<(), Box<dyn std::error::Error>> {
+        let rope = sample()?;
+        assert_eq!(
+            KnotStats {
+                unique: 13,
+                revisits: 1,
+                min: Point { x: 0, y: -4 },
+                max: Point { x: 4, y: 0 },
+                longest_run: 3,
+            },
+            stats(rope.trail(1)?)
+        );
+        assert_eq!(5, stats(rope.trail(0)?).longest_run);
+        Ok(())
+    }
+
+    #[test]
+    fn ascii() -> Result<(), Box<dyn std::error::Error>> {
+        // the same picture as the puzzle, with y going down
+        assert_eq!(
+            "..11.\n...11\n.1111\n....1\ns111.\n",
+            render_ascii(&sample()?, &[1])?
+        );
+        // the tail is drawn over the head wherever they both went
+        assert_eq!(
+            ".H11H.\n.HH11H\nH1111H\n....1.\ns111H.\n",
+            render_ascii(&sample()?, &[0, 1])?
+        );
+        assert!(render_ascii(&sample()?, &[2]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn image() -> Result<(), Box<dyn std::error::Error>> {
+        let image = render_image(&sample()?, &[1], 3)?;
+        assert_eq!((15, 15), image.dimensions());
+        assert_eq!(&image::Rgb([255, 255, 255]), image.get_pixel(0, 14));
+        assert_eq!(&image::Rgb([64, 192, 64]), image.get_pixel(3, 14));
+        assert_eq!(&image::Rgb([0, 0, 0]), image.get_pixel(0, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn frames() {
+        assert_eq!(
+            "7 1,-2 0,0",
+            frame(7, &[Point { x: 1, y: -2 }, Point { x: 0, y: 0 }])
+        );
+    }
+}
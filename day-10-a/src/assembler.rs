@@ -0,0 +1,228 @@
+use std::{collections::HashMap, error::Error, fmt};
+
+use crate::vm::{register_index, Instruction, Operand};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssembleError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl Error for AssembleError {}
+
+// a word of source with its 1-based position
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+    column: usize,
+}
+
+impl Token<'_> {
+    fn error(&self, message: String) -> AssembleError {
+        AssembleError {
+            line: self.line,
+            column: self.column,
+            message,
+        }
+    }
+}
+
+// one instruction per line, "name:" labels the next instruction and ; starts a comment
+pub fn assemble(source: &str) -> Result<Vec<Instruction>, AssembleError> {
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let code = line.split(';').next().unwrap_or("");
+        let mut tokens = tokenize(code, i + 1);
+        while let Some(label) = tokens
+            .first()
+            .and_then(|token| token.text.strip_suffix(':'))
+        {
+            let token = tokens.remove(0);
+            if label.is_empty() || !label.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                Err(token.error(format!("bad label name {:?}", label)))?
+            }
+            if labels.insert(label, statements.len()).is_some() {
+                Err(token.error(format!("label {} is already defined", label)))?
+            }
+        }
+        if !tokens.is_empty() {
+            statements.push(tokens);
+        }
+    }
+
+    statements
+        .iter()
+        .map(|tokens| parse_instruction(tokens, &labels))
+        .collect()
+}
+
+fn tokenize(code: &str, line: usize) -> Vec<Token<'_>> {
+    let mut results = Vec::new();
+    let mut start = None;
+    for (i, c) in code.char_indices().chain([(code.len(), ' ')]) {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                results.push(Token {
+                    text: &code[s..i],
+                    line,
+                    column: code[..s].chars().count() + 1,
+                });
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    results
+}
+
+fn parse_instruction(
+    tokens: &[Token],
+    labels: &HashMap<&str, usize>,
+) -> Result<Instruction, AssembleError> {
+    let mnemonic = tokens[0];
+    let args = &tokens[1..];
+    let expect = |count: usize| {
+        if args.len() == count {
+            Ok(())
+        } else {
+            Err(mnemonic.error(format!(
+                "{} takes {} operands but got {}",
+                mnemonic.text,
+                count,
+                args.len()
+            )))
+        }
+    };
+    let register = |token: &Token| {
+        let mut chars = token.text.chars();
+        match (chars.next().and_then(register_index), chars.next()) {
+            (Some(r), None) => Ok(r),
+            _ => Err(token.error(format!("expected a register a-z but got {}", token.text))),
+        }
+    };
+    let value = |token: &Token| {
+        token
+            .text
+            .parse::<i64>()
+            .map_err(|e| token.error(format!("bad number {}: {}", token.text, e)))
+    };
+    let operand = |token: &Token| {
+        register(token)
+            .map(Operand::Register)
+            .or_else(|_| value(token).map(Operand::Value))
+            .map_err(|_| {
+                token.error(format!(
+                    "expected a register or number but got {}",
+                    token.text
+                ))
+            })
+    };
+    let label = |token: &Token| {
+        labels
+            .get(token.text)
+            .copied()
+            .ok_or(token.error(format!("no label named {}", token.text)))
+    };
+
+    Ok(match mnemonic.text {
+        "noop" => {
+            expect(0)?;
+            Instruction::Noop
+        }
+        "addx" => {
+            expect(1)?;
+            Instruction::Addx(value(&args[0])?)
+        }
+        "add" => {
+            expect(2)?;
+            Instruction::Add(register(&args[0])?, operand(&args[1])?)
+        }
+        "mul" => {
+            expect(2)?;
+            Instruction::Mul(register(&args[0])?, operand(&args[1])?)
+        }
+        "mod" => {
+            expect(2)?;
+            Instruction::Mod(register(&args[0])?, operand(&args[1])?)
+        }
+        "jmp" => {
+            expect(1)?;
+            Instruction::Jmp(label(&args[0])?)
+        }
+        "jnz" => {
+            expect(2)?;
+            Instruction::Jnz(operand(&args[0])?, label(&args[1])?)
+        }
+        _ => Err(mnemonic.error(format!("unrecognized instruction {}", mnemonic.text)))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::vm::{Costs, Machine, Tick};
+
+    #[test]
+    fn loops_and_labels() -> Result<(), Box<dyn Error>> {
+        // x = 3^4 by repeated multiplication
+        let program = assemble(
+            r"    add a 4
+    add x 2   ; x starts at 1
+loop: mul x 3
+    add a -1
+    jnz a loop
+    mod x 10",
+        )?;
+        assert_eq!(Instruction::Jnz(Operand::Register(0), 2), program[4]);
+        let mut machine = Machine::new(Costs::default());
+        machine.run(&program, &mut |_: &Tick| (), Some(1000))?;
+        assert_eq!((3 * 81) % 10, machine.register('x')?);
+        assert_eq!(0, machine.register('a')?);
+        assert_eq!(15, machine.cycle());
+        Ok(())
+    }
+
+    #[test]
+    fn error_positions() {
+        let error = |source: &str| assemble(source).err().unwrap().to_string();
+        assert_eq!(
+            "line 2, column 3: unrecognized instruction addy",
+            error("noop\n  addy 3")
+        );
+        assert_eq!(
+            "line 1, column 5: expected a register a-z but got X",
+            error("mul X 2")
+        );
+        assert_eq!(
+            "line 1, column 7: expected a register or number but got 2x",
+            error("add a 2x")
+        );
+        assert_eq!(
+            "line 3, column 5: no label named end",
+            error("a:\nnoop\njmp end")
+        );
+        assert_eq!(
+            "line 1, column 1: addx takes 1 operands but got 0",
+            error("addx")
+        );
+        assert_eq!(
+            "line 2, column 1: label a is already defined",
+            error("a: noop\na: noop")
+        );
+    }
+}
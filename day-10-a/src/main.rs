@@ -1,80 +1,115 @@
+mod assembler;
+mod vm;
+
 use std::{
+    env,
     error::Error,
-    io::{self, BufRead, BufReader},
+    io::{self, BufReader},
 };
 
-enum Instruction {
-    Noop,
-    Addx(i32),
-}
+use assembler::assemble;
+use vm::{Costs, Instruction, Machine, Sampler, Tick};
 
-#[derive(Debug)]
-struct State {
-    cycle: usize,
-    register_x: i32,
-}
+// anything still running after this long is probably stuck in a loop
+const MAX_CYCLES: usize = 1000000;
 
-impl State {
-    fn new() -> Self {
-        Self {
-            cycle: 0,
-            register_x: 1,
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut r = BufReader::new(io::stdin());
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+    let (costs, args) = parse_costs(&args)?;
+    match args {
+        [] => {
+            do_it(&mut r, &costs)?;
         }
-    }
-
-    fn execute(&mut self, instruction: Instruction, mut f: impl FnMut(&State)) {
-        match instruction {
-            Instruction::Noop => {
-                self.cycle += 1;
-                f(&self);
-            }
-            Instruction::Addx(delta) => {
-                self.cycle += 1;
-                f(&self);
-                self.cycle += 1;
-                f(&self);
-                self.register_x += delta;
+        // every cycle as it happens
+        ["trace"] => {
+            let program = load_program(&mut r)?;
+            let mut machine = Machine::new(costs);
+            machine.run(
+                &program,
+                &mut |tick: &Tick| println!("{}", tick),
+                Some(MAX_CYCLES),
+            )?;
+            println!(
+                "finished after {} cycles, x = {}",
+                machine.cycle(),
+                machine.register('x')?
+            );
+        }
+        // x during each of the given cycles, rather than just every 40 from 20
+        ["sample", cycles @ ..] => {
+            let cycles = cycles
+                .iter()
+                .map(|cycle| cycle.parse::<usize>())
+                .collect::<Result<Vec<_>, _>>()?;
+            for (cycle, x) in cycles.iter().zip(sampled(&mut r, &costs, &cycles)?) {
+                println!(
+                    "cycle = {}, x = {}, result = {}",
+                    cycle,
+                    x,
+                    *cycle as i64 * x
+                );
             }
         }
+        _ => Err(format!("unrecognized arguments: {:?}", args))?,
+    };
+    Ok(())
+}
+
+// any number of "--cost mnemonic=cycles" flags before the command
+fn parse_costs<'a, 'b>(args: &'b [&'a str]) -> Result<(Costs, &'b [&'a str]), Box<dyn Error>> {
+    let mut result = Costs::default();
+    let mut args = args;
+    while let ["--cost", cost, rest @ ..] = args {
+        let (mnemonic, cycles) = cost
+            .split_once('=')
+            .ok_or(format!("expected mnemonic=cycles but got {}", cost))?;
+        result.set(mnemonic, cycles.parse()?)?;
+        args = rest;
     }
+    Ok((result, args))
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let mut r = BufReader::new(io::stdin());
-    do_it(&mut r)?;
-    Ok(())
+fn load_program(r: &mut impl std::io::Read) -> Result<Vec<Instruction>, Box<dyn Error>> {
+    let mut source = String::new();
+    r.read_to_string(&mut source)?;
+    Ok(assemble(&source)?)
 }
 
-fn do_it(r: &mut impl std::io::Read) -> Result<i32, Box<dyn Error>> {
-    let noop_re = regex::Regex::new("^noop$")?;
-    let addx_re = regex::Regex::new("^addx (-?[0-9]+)$")?;
+fn sampled(
+    r: &mut impl std::io::Read,
+    costs: &Costs,
+    cycles: &[usize],
+) -> Result<Vec<i64>, Box<dyn Error>> {
+    let program = load_program(r)?;
+    let mut sampler = Sampler::default();
+    Machine::new(costs.clone()).run(&program, &mut sampler, Some(MAX_CYCLES))?;
+    Ok(sampler.sampled(cycles)?)
+}
 
-    let state = &mut State::new();
+fn do_it(r: &mut impl std::io::Read, costs: &Costs) -> Result<i64, Box<dyn Error>> {
+    let program = load_program(r)?;
+    let mut machine = Machine::new(costs.clone());
     let mut total = 0;
-
-    for line in BufReader::new(r).lines() {
-        let line = line?;
-        println!("line = {}", line);
-
-        let instruction = if noop_re.is_match(&line) {
-            Ok(Instruction::Noop)
-        } else if let Some(captures) = addx_re.captures(&line) {
-            Ok(Instruction::Addx(captures[1].parse()?))
-        } else {
-            Err(format!("no match: {}", line))
-        }?;
-        state.execute(instruction, |updated_state| {
-            println!("{:?}", updated_state);
-            if updated_state.cycle == 20
-                || (updated_state.cycle >= 20 && (updated_state.cycle - 20) % 40 == 0)
-            {
-                let result = updated_state.cycle as i32 * updated_state.register_x;
-                println!("state = {:?}, result = {}", updated_state, result);
+    machine.run(
+        &program,
+        &mut |tick: &Tick| {
+            println!("{}", tick);
+            if tick.cycle >= 20 && (tick.cycle - 20).is_multiple_of(40) {
+                let result = tick.cycle as i64 * tick.x();
+                println!(
+                    "cycle = {}, x = {}, result = {}",
+                    tick.cycle,
+                    tick.x(),
+                    result
+                );
                 total += result;
             }
-        });
-    }
-    println!("final state = {:?}", state);
+        },
+        Some(MAX_CYCLES),
+    )?;
+    println!("final state = {:?}", machine);
     println!("final result = {}", total);
 
     Ok(total)
@@ -84,12 +119,7 @@ fn do_it(r: &mut impl std::io::Read) -> Result<i32, Box<dyn Error>> {
 mod tests {
     use super::*;
 
-    #[test]
-    fn sample() -> Result<(), Box<dyn Error>> {
-        assert_eq!(
-            13140,
-            do_it(
-                &mut r"addx 15
+    const SAMPLE: &str = r"addx 15
 addx -11
 addx 6
 addx -3
@@ -234,10 +264,25 @@ addx -6
 addx -11
 noop
 noop
-noop"
-                    .as_bytes(),
+noop";
+
+    #[test]
+    fn sample() -> Result<(), Box<dyn Error>> {
+        assert_eq!(13140, do_it(&mut SAMPLE.as_bytes(), &Costs::default())?);
+        Ok(())
+    }
+
+    #[test]
+    fn sample_any_cycles() -> Result<(), Box<dyn Error>> {
+        assert_eq!(
+            vec![1, 21, 19, 18, 21, 16, 18],
+            sampled(
+                &mut SAMPLE.as_bytes(),
+                &Costs::default(),
+                &[1, 20, 60, 100, 140, 180, 220]
             )?
         );
+        assert!(sampled(&mut SAMPLE.as_bytes(), &Costs::default(), &[241]).is_err());
         Ok(())
     }
 }
@@ -0,0 +1,152 @@
+use std::fmt::Write;
+
+use crate::vm::{Observer, Sampler, Tick};
+
+// draws one pixel per cycle, left to right and top to bottom, starting over once the screen is full
+#[derive(Debug, Clone)]
+pub struct Crt {
+    width: usize,
+    height: usize,
+    // how many columns the sprite covers, centred on x, with any extra column going to the right
+    sprite_width: usize,
+    pub lit: char,
+    pub dark: char,
+    pixels: Vec<bool>,
+    sampler: Sampler,
+}
+
+impl Default for Crt {
+    fn default() -> Self {
+        Self::new(40, 6, 3).unwrap()
+    }
+}
+
+impl Crt {
+    pub fn new(width: usize, height: usize, sprite_width: usize) -> Result<Crt, String> {
+        if width == 0 || height == 0 || sprite_width == 0 {
+            Err(format!(
+                "width, height and sprite width all have to be positive, width = {}, height = {}, sprite width = {}",
+                width, height, sprite_width
+            ))?
+        }
+        Ok(Crt {
+            width,
+            height,
+            sprite_width,
+            lit: '#',
+            dark: '.',
+            pixels: vec![false; width * height],
+            sampler: Sampler::default(),
+        })
+    }
+
+    pub fn render(&self) -> String {
+        let mut result = String::new();
+        for row in self.pixels.chunks(self.width) {
+            for lit in row.iter() {
+                result.push(if *lit { self.lit } else { self.dark });
+            }
+            result.push('\n');
+        }
+        result
+    }
+
+    // true for each lit pixel, a row at a time
+    pub fn rows(&self) -> Vec<&[bool]> {
+        self.pixels.chunks(self.width).collect()
+    }
+
+    // plain text portable bitmap, 1 is a lit pixel
+    pub fn pbm(&self) -> String {
+        let mut result = format!("P1\n{} {}\n", self.width, self.height);
+        for row in self.pixels.chunks(self.width) {
+            let row = row
+                .iter()
+                .map(|lit| if *lit { "1" } else { "0" })
+                .collect::<Vec<_>>();
+            writeln!(result, "{}", row.join(" ")).unwrap();
+        }
+        result
+    }
+
+    // each pixel as a square of scale pixels, lit ones white
+    pub fn image(&self, scale: u32) -> Result<image::GrayImage, String> {
+        if scale == 0 {
+            Err("scale must be positive")?
+        }
+        Ok(image::GrayImage::from_fn(
+            self.width as u32 * scale,
+            self.height as u32 * scale,
+            |x, y| {
+                let i = (y / scale) as usize * self.width + (x / scale) as usize;
+                image::Luma([if self.pixels[i] { 255 } else { 0 }])
+            },
+        ))
+    }
+
+    // the value of x during each of the given cycles
+    pub fn sampled(&self, cycles: &[usize]) -> Result<Vec<i64>, String> {
+        self.sampler.sampled(cycles)
+    }
+}
+
+impl Observer for Crt {
+    fn on_cycle(&mut self, tick: &Tick) {
+        let i = (tick.cycle - 1) % self.pixels.len();
+        let column = (i % self.width) as i64;
+        let left = tick.x() - (self.sprite_width as i64 - 1) / 2;
+        self.pixels[i] = column >= left && column < left + self.sprite_width as i64;
+        self.sampler.on_cycle(tick);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::vm::{Costs, Instruction, Machine};
+
+    fn run(crt: Crt, program: &[Instruction]) -> Result<Crt, String> {
+        let mut crt = crt;
+        Machine::new(Costs::default()).run(program, &mut crt, None)?;
+        Ok(crt)
+    }
+
+    #[test]
+    fn geometry() -> Result<(), String> {
+        // x is 1, 1, 1, 3, 3, 0 over the 6 cycles
+        let program = [
+            Instruction::Noop,
+            Instruction::Addx(2),
+            Instruction::Addx(-3),
+            Instruction::Noop,
+        ];
+        let mut crt = Crt::new(4, 2, 3)?;
+        crt.lit = 'X';
+        crt.dark = ' ';
+        let crt = run(crt, &program)?;
+        assert_eq!("XXXX\n X  \n", crt.render());
+        assert_eq!("P1\n4 2\n1 1 1 1\n0 1 0 0\n", crt.pbm());
+        assert_eq!(vec![1, 3, 0], crt.sampled(&[1, 4, 6])?);
+        assert!(crt.sampled(&[0]).is_err());
+        assert!(crt.sampled(&[7]).is_err());
+
+        // a wider sprite, and the screen wrapping back around to the top
+        let crt = run(Crt::new(3, 1, 4)?, &program)?;
+        assert_eq!("..#\n", crt.render());
+        let crt = run(Crt::new(3, 1, 1)?, &program)?;
+        assert_eq!("...\n", crt.render());
+        assert!(Crt::new(0, 6, 3).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn image() -> Result<(), String> {
+        let crt = run(Crt::new(2, 1, 1)?, &[Instruction::Addx(0)])?;
+        let image = crt.image(2)?;
+        assert_eq!((4, 2), image.dimensions());
+        assert_eq!(&image::Luma([0]), image.get_pixel(1, 1));
+        assert_eq!(&image::Luma([255]), image.get_pixel(2, 1));
+        Ok(())
+    }
+}
@@ -1,87 +1,135 @@
+mod assembler;
+mod crt;
+mod ocr;
+mod vm;
+
 use std::{
+    env,
     error::Error,
-    io::{self, BufRead, BufReader},
+    fs,
+    io::{self, BufReader},
 };
 
-enum Instruction {
-    Noop,
-    Addx(i32),
-}
+use assembler::assemble;
+use crt::Crt;
+use vm::{Costs, Instruction, Machine, Tick};
 
-#[derive(Debug)]
-struct State {
-    cycle: usize,
-    register_x: i32,
-}
+// anything still running after this long is probably stuck in a loop
+const MAX_CYCLES: usize = 1000000;
 
-impl State {
-    fn new() -> Self {
-        Self {
-            cycle: 0,
-            register_x: 1,
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut r = BufReader::new(io::stdin());
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+    let (costs, crt, args) = parse_flags(&args)?;
+    match args {
+        [] => {
+            let crt = do_it(&mut r, &costs, crt)?;
+            // only the puzzle's screen size spells anything
+            match ocr::decode(&crt.rows()) {
+                Ok(letters) => println!("letters = {}", letters),
+                Err(e) => println!("can't read any letters: {}", e),
+            }
         }
-    }
+        // every cycle as it happens
+        ["trace"] => {
+            let program = load_program(&mut r)?;
+            let mut machine = Machine::new(costs);
+            machine.run(
+                &program,
+                &mut |tick: &Tick| println!("{}", tick),
+                Some(MAX_CYCLES),
+            )?;
+            println!(
+                "finished after {} cycles, x = {}",
+                machine.cycle(),
+                machine.register('x')?
+            );
+        }
+        ["pbm", path] => {
+            fs::write(path, do_it(&mut r, &costs, crt)?.pbm())?;
+        }
+        ["png", path, scale] => {
+            do_it(&mut r, &costs, crt)?
+                .image(scale.parse()?)?
+                .save(path)?;
+        }
+        // x during each of the given cycles
+        ["sample", cycles @ ..] => {
+            let cycles = cycles
+                .iter()
+                .map(|cycle| cycle.parse::<usize>())
+                .collect::<Result<Vec<_>, _>>()?;
+            let crt = do_it(&mut r, &costs, crt)?;
+            for (cycle, x) in cycles.iter().zip(crt.sampled(&cycles)?) {
+                println!("cycle = {}, x = {}", cycle, x);
+            }
+        }
+        _ => Err(format!("unrecognized arguments: {:?}", args))?,
+    };
+    Ok(())
+}
 
-    fn execute(&mut self, instruction: Instruction, mut f: impl FnMut(&State)) {
-        match instruction {
-            Instruction::Noop => {
-                self.cycle += 1;
-                f(&self);
+// any number of "--cost mnemonic=cycles", "--width N", "--height N", "--sprite N", "--lit C" and
+// "--dark C" flags before the command
+fn parse_flags<'a, 'b>(args: &'b [&'a str]) -> Result<(Costs, Crt, &'b [&'a str]), Box<dyn Error>> {
+    let mut costs = Costs::default();
+    let (mut width, mut height, mut sprite_width) = (40, 6, 3);
+    let (mut lit, mut dark) = ('#', '.');
+    let mut args = args;
+    loop {
+        args = match args {
+            ["--cost", cost, rest @ ..] => {
+                let (mnemonic, cycles) = cost
+                    .split_once('=')
+                    .ok_or(format!("expected mnemonic=cycles but got {}", cost))?;
+                costs.set(mnemonic, cycles.parse()?)?;
+                rest
             }
-            Instruction::Addx(delta) => {
-                self.cycle += 1;
-                f(&self);
-                self.cycle += 1;
-                f(&self);
-                self.register_x += delta;
+            ["--width", value, rest @ ..] => {
+                width = value.parse()?;
+                rest
             }
-        }
+            ["--height", value, rest @ ..] => {
+                height = value.parse()?;
+                rest
+            }
+            ["--sprite", value, rest @ ..] => {
+                sprite_width = value.parse()?;
+                rest
+            }
+            ["--lit", value, rest @ ..] => {
+                lit = value.parse()?;
+                rest
+            }
+            ["--dark", value, rest @ ..] => {
+                dark = value.parse()?;
+                rest
+            }
+            _ => break,
+        };
     }
+    let mut crt = Crt::new(width, height, sprite_width)?;
+    crt.lit = lit;
+    crt.dark = dark;
+    Ok((costs, crt, args))
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let mut r = BufReader::new(io::stdin());
-    do_it(&mut r)?;
-    Ok(())
+fn load_program(r: &mut impl std::io::Read) -> Result<Vec<Instruction>, Box<dyn Error>> {
+    let mut source = String::new();
+    r.read_to_string(&mut source)?;
+    Ok(assemble(&source)?)
 }
 
-fn do_it(r: &mut impl std::io::Read) -> Result<String, Box<dyn Error>> {
-    let noop_re = regex::Regex::new("^noop$")?;
-    let addx_re = regex::Regex::new("^addx (-?[0-9]+)$")?;
-
-    let state = &mut State::new();
-    let mut display = String::new();
-
-    let mut screen_x = 0;
-    for line in BufReader::new(r).lines() {
-        let line = line?;
-        println!("line = {}", line);
+fn do_it(r: &mut impl std::io::Read, costs: &Costs, crt: Crt) -> Result<Crt, Box<dyn Error>> {
+    let program = load_program(r)?;
+    let mut machine = Machine::new(costs.clone());
+    let mut crt = crt;
+    machine.run(&program, &mut crt, Some(MAX_CYCLES))?;
+    println!("final state = {:?}", machine);
+    println!("final display =\n{}", crt.render());
 
-        let instruction = if noop_re.is_match(&line) {
-            Ok(Instruction::Noop)
-        } else if let Some(captures) = addx_re.captures(&line) {
-            Ok(Instruction::Addx(captures[1].parse()?))
-        } else {
-            Err(format!("no match: {}", line))
-        }?;
-        state.execute(instruction, |updated_state| {
-            println!("{:?}", updated_state);
-            let color = if (screen_x - updated_state.register_x).abs() <= 1 {
-                '#'
-            } else {
-                '.'
-            };
-            display += color.to_string().as_str();
-            if screen_x == 39 {
-                display += "\n";
-            }
-            screen_x = (screen_x + 1) % 40;
-        });
-    }
-    println!("final state = {:?}", state);
-    println!("final display =\n{}", display);
-
-    Ok(display)
+    Ok(crt)
 }
 
 #[cfg(test)]
@@ -246,8 +294,28 @@ noop
 noop
 noop"
                     .as_bytes(),
+                &Costs::default(),
+                Crt::default(),
             )?
+            .render()
         );
         Ok(())
     }
+
+    #[test]
+    fn real_input() -> Result<(), Box<dyn Error>> {
+        let crt = do_it(
+            &mut std::fs::File::open("input")?,
+            &Costs::default(),
+            Crt::default(),
+        )?;
+        assert_eq!("EHPZPJGL", ocr::decode(&crt.rows())?);
+
+        // drawing it differently doesn't change what it says
+        let (_, crt, _) = parse_flags(&["--lit", "X", "--dark", " "])?;
+        let crt = do_it(&mut std::fs::File::open("input")?, &Costs::default(), crt)?;
+        assert!(crt.render().starts_with("XXXX "));
+        assert_eq!("EHPZPJGL", ocr::decode(&crt.rows())?);
+        Ok(())
+    }
 }
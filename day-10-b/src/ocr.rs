@@ -0,0 +1,120 @@
+// the letters the puzzles draw, each 4 pixels wide and 6 tall with a blank column after it
+const WIDTH: usize = 4;
+const HEIGHT: usize = 6;
+const FONT: [(char, [&str; HEIGHT]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+// reads the letters off the rows of a display, true where a pixel is lit, whatever it's drawn as
+pub fn decode(rows: &[&[bool]]) -> Result<String, String> {
+    if rows.len() != HEIGHT {
+        Err(format!(
+            "expected {} rows but the display has {}",
+            HEIGHT,
+            rows.len()
+        ))?
+    }
+    let width = rows[0].len();
+    if rows.iter().any(|row| row.len() != width) {
+        Err("display rows aren't all the same width")?
+    }
+
+    let mut result = String::new();
+    for (i, left) in (0..width).step_by(WIDTH + 1).enumerate() {
+        let cell = rows
+            .iter()
+            .map(|row| &row[left..width.min(left + WIDTH + 1)])
+            .collect::<Vec<_>>();
+        let glyph = FONT.iter().find(|(_, pixels)| {
+            cell.iter().zip(pixels.iter()).all(|(row, pixels)| {
+                // the blank column after it can be missing at the right edge
+                row.len() >= WIDTH
+                    && row[WIDTH..].iter().all(|lit| !lit)
+                    && row
+                        .iter()
+                        .zip(pixels.chars())
+                        .all(|(lit, c)| *lit == (c == '#'))
+            })
+        });
+        match glyph {
+            Some((c, _)) => result.push(*c),
+            None => Err(format!(
+                "no letter matches cell {} at column {}:\n{}",
+                i + 1,
+                left + 1,
+                cell.iter()
+                    .map(|row| row
+                        .iter()
+                        .map(|lit| if *lit { '#' } else { '.' })
+                        .collect::<String>())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ))?,
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a display drawn with # for lit pixels
+    fn decode_drawing(s: &str) -> Result<String, String> {
+        let rows = s
+            .lines()
+            .map(|line| line.chars().map(|c| c == '#').collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        decode(&rows.iter().map(|row| row.as_slice()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn letters() -> Result<(), String> {
+        assert_eq!(
+            "HI",
+            decode_drawing(
+                r"#..#..###.
+#..#...#..
+####...#..
+#..#...#..
+#..#...#..
+#..#..###."
+            )?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_glyph() {
+        assert_eq!(
+            "no letter matches cell 2 at column 6:\n##...\n##...\n.....\n.....\n.....\n.....",
+            decode_drawing(
+                r"#..#.##...
+#..#.##...
+####......
+#..#......
+#..#......
+#..#......"
+            )
+            .err()
+            .unwrap()
+        );
+        assert!(decode_drawing("#..#\n#..#").is_err());
+    }
+}
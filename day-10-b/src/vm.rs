@@ -0,0 +1,364 @@
+use std::{collections::HashMap, fmt};
+
+pub const REGISTERS: usize = 26;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Register(usize),
+    Value(i64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Noop,
+    // adds to x, the only register the original device had
+    Addx(i64),
+    Add(usize, Operand),
+    Mul(usize, Operand),
+    Mod(usize, Operand),
+    // jumps go to an instruction index, labels are resolved by the assembler
+    Jmp(usize),
+    Jnz(Operand, usize),
+}
+
+impl Instruction {
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Noop => "noop",
+            Instruction::Addx(_) => "addx",
+            Instruction::Add(_, _) => "add",
+            Instruction::Mul(_, _) => "mul",
+            Instruction::Mod(_, _) => "mod",
+            Instruction::Jmp(_) => "jmp",
+            Instruction::Jnz(_, _) => "jnz",
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Register(r) => write!(f, "{}", register_name(*r)),
+            Operand::Value(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.mnemonic())?;
+        match self {
+            Instruction::Noop => Ok(()),
+            Instruction::Addx(v) => write!(f, " {}", v),
+            Instruction::Add(r, o) | Instruction::Mul(r, o) | Instruction::Mod(r, o) => {
+                write!(f, " {} {}", register_name(*r), o)
+            }
+            Instruction::Jmp(target) => write!(f, " @{}", target),
+            Instruction::Jnz(o, target) => write!(f, " {} @{}", o, target),
+        }
+    }
+}
+
+pub fn register_index(name: char) -> Option<usize> {
+    if name.is_ascii_lowercase() {
+        Some(name as usize - 'a' as usize)
+    } else {
+        None
+    }
+}
+
+pub fn register_name(index: usize) -> char {
+    (b'a' + index as u8) as char
+}
+
+// how many cycles each instruction takes, by mnemonic
+#[derive(Debug, Clone)]
+pub struct Costs(HashMap<&'static str, usize>);
+
+impl Default for Costs {
+    fn default() -> Self {
+        Self(HashMap::from([
+            ("noop", 1),
+            ("addx", 2),
+            ("add", 1),
+            ("mul", 1),
+            ("mod", 1),
+            ("jmp", 1),
+            ("jnz", 1),
+        ]))
+    }
+}
+
+impl Costs {
+    pub fn set(&mut self, mnemonic: &str, cycles: usize) -> Result<(), String> {
+        if cycles == 0 {
+            Err(format!("{} has to take at least one cycle", mnemonic))?
+        }
+        let key = self
+            .0
+            .keys()
+            .find(|key| **key == mnemonic)
+            .copied()
+            .ok_or(format!("unrecognized instruction: {}", mnemonic))?;
+        self.0.insert(key, cycles);
+        Ok(())
+    }
+
+    pub fn get(&self, instruction: &Instruction) -> usize {
+        self.0[instruction.mnemonic()]
+    }
+}
+
+// what an observer gets told about each cycle, while it's happening and before the instruction
+// takes effect
+#[derive(Debug)]
+pub struct Tick<'a> {
+    pub cycle: usize,
+    pub pc: usize,
+    pub instruction: &'a Instruction,
+    pub registers: &'a [i64; REGISTERS],
+}
+
+impl Tick<'_> {
+    pub fn x(&self) -> i64 {
+        self.registers[register_index('x').unwrap()]
+    }
+}
+
+impl fmt::Display for Tick<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cycle {}: pc = {}, {}",
+            self.cycle, self.pc, self.instruction
+        )?;
+        // only the registers that have been touched, to keep it readable
+        for (i, value) in self.registers.iter().enumerate() {
+            if *value != 0 || register_name(i) == 'x' {
+                write!(f, ", {} = {}", register_name(i), value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub trait Observer {
+    fn on_cycle(&mut self, tick: &Tick);
+}
+
+impl<F: FnMut(&Tick)> Observer for F {
+    fn on_cycle(&mut self, tick: &Tick) {
+        self(tick)
+    }
+}
+
+// remembers x during every cycle, so it can be looked up afterwards for any of them
+#[derive(Debug, Clone, Default)]
+pub struct Sampler {
+    // starting with cycle 1
+    xs: Vec<i64>,
+}
+
+impl Sampler {
+    pub fn sampled(&self, cycles: &[usize]) -> Result<Vec<i64>, String> {
+        cycles
+            .iter()
+            .map(|cycle| {
+                cycle
+                    .checked_sub(1)
+                    .and_then(|i| self.xs.get(i))
+                    .copied()
+                    .ok_or(format!(
+                        "no cycle {}, the program ran for {}",
+                        cycle,
+                        self.xs.len()
+                    ))
+            })
+            .collect()
+    }
+}
+
+impl Observer for Sampler {
+    fn on_cycle(&mut self, tick: &Tick) {
+        self.xs.push(tick.x());
+    }
+}
+
+#[derive(Debug)]
+pub struct Machine {
+    costs: Costs,
+    registers: [i64; REGISTERS],
+    pc: usize,
+    cycle: usize,
+}
+
+impl Machine {
+    pub fn new(costs: Costs) -> Self {
+        let mut registers = [0; REGISTERS];
+        registers[register_index('x').unwrap()] = 1;
+        Self {
+            costs,
+            registers,
+            pc: 0,
+            cycle: 0,
+        }
+    }
+
+    pub fn register(&self, name: char) -> Result<i64, String> {
+        register_index(name)
+            .map(|i| self.registers[i])
+            .ok_or(format!("no register {}", name))
+    }
+
+    pub fn cycle(&self) -> usize {
+        self.cycle
+    }
+
+    // runs one instruction, returning false once the program has finished
+    pub fn step(
+        &mut self,
+        program: &[Instruction],
+        observer: &mut impl Observer,
+    ) -> Result<bool, String> {
+        let instruction = match program.get(self.pc) {
+            Some(instruction) => instruction,
+            None => return Ok(false),
+        };
+        for _ in 0..self.costs.get(instruction) {
+            self.cycle += 1;
+            observer.on_cycle(&Tick {
+                cycle: self.cycle,
+                pc: self.pc,
+                instruction,
+                registers: &self.registers,
+            });
+        }
+
+        let error = |message: &str| format!("cycle {}, pc {}: {}", self.cycle, self.pc, message);
+        let value = |o: &Operand| match o {
+            Operand::Register(r) => self.registers[*r],
+            Operand::Value(v) => *v,
+        };
+        let mut next = self.pc + 1;
+        match instruction {
+            Instruction::Noop => (),
+            Instruction::Addx(v) => {
+                let x = register_index('x').unwrap();
+                self.registers[x] = self.registers[x].checked_add(*v).ok_or(error("overflow"))?;
+            }
+            Instruction::Add(r, o) => {
+                self.registers[*r] = self.registers[*r]
+                    .checked_add(value(o))
+                    .ok_or(error("overflow"))?;
+            }
+            Instruction::Mul(r, o) => {
+                self.registers[*r] = self.registers[*r]
+                    .checked_mul(value(o))
+                    .ok_or(error("overflow"))?;
+            }
+            Instruction::Mod(r, o) => {
+                let divisor = value(o);
+                if divisor == 0 {
+                    Err(error("mod by zero"))?
+                }
+                self.registers[*r] = self.registers[*r]
+                    .checked_rem_euclid(divisor)
+                    .ok_or(error("overflow"))?;
+            }
+            Instruction::Jmp(target) => next = *target,
+            Instruction::Jnz(o, target) => {
+                if value(o) != 0 {
+                    next = *target;
+                }
+            }
+        }
+        self.pc = next;
+        Ok(true)
+    }
+
+    // stops with an error instead of looping forever if the program is still going after max_cycles
+    pub fn run(
+        &mut self,
+        program: &[Instruction],
+        observer: &mut impl Observer,
+        max_cycles: Option<usize>,
+    ) -> Result<(), String> {
+        while self.step(program, observer)? {
+            if max_cycles.is_some_and(|max| self.cycle > max) {
+                Err(format!(
+                    "still running after {} cycles, pc = {}",
+                    self.cycle, self.pc
+                ))?
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn costs_and_ticks() -> Result<(), String> {
+        let program = [
+            Instruction::Noop,
+            Instruction::Addx(3),
+            Instruction::Addx(-5),
+        ];
+        let mut machine = Machine::new(Costs::default());
+        let mut xs = Vec::new();
+        machine.run(
+            &program,
+            &mut |tick: &Tick| xs.push((tick.cycle, tick.x())),
+            None,
+        )?;
+        assert_eq!(vec![(1, 1), (2, 1), (3, 1), (4, 4), (5, 4)], xs);
+        assert_eq!(-1, machine.register('x')?);
+
+        let mut costs = Costs::default();
+        costs.set("addx", 3)?;
+        let mut machine = Machine::new(costs);
+        machine.run(&program, &mut |_: &Tick| (), None)?;
+        assert_eq!(7, machine.cycle());
+        assert!(Costs::default().set("addx", 0).is_err());
+        assert!(Costs::default().set("nope", 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        let a = register_index('a').unwrap();
+        let mut machine = Machine::new(Costs::default());
+        assert_eq!(
+            "cycle 1, pc 0: mod by zero",
+            machine
+                .run(
+                    &[Instruction::Mod(a, Operand::Value(0))],
+                    &mut |_: &Tick| (),
+                    None
+                )
+                .err()
+                .unwrap()
+        );
+        let mut machine = Machine::new(Costs::default());
+        assert_eq!(
+            "cycle 2, pc 1: overflow",
+            machine
+                .run(
+                    &[
+                        Instruction::Add(a, Operand::Value(i64::MIN)),
+                        Instruction::Mod(a, Operand::Value(-1)),
+                    ],
+                    &mut |_: &Tick| (),
+                    None
+                )
+                .err()
+                .unwrap()
+        );
+        let mut machine = Machine::new(Costs::default());
+        assert!(machine
+            .run(&[Instruction::Jmp(0)], &mut |_: &Tick| (), Some(100))
+            .is_err());
+    }
+}
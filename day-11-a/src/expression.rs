@@ -0,0 +1,249 @@
+use std::{fmt, str::FromStr};
+
+use num_bigint::BigUint;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+impl Operator {
+    fn symbol(self) -> char {
+        match self {
+            Operator::Add => '+',
+            Operator::Subtract => '-',
+            Operator::Multiply => '*',
+            Operator::Divide => '/',
+            Operator::Remainder => '%',
+        }
+    }
+}
+
+// the right hand side of "new = ...", in terms of old
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expression {
+    Old,
+    Constant(u64),
+    Binary(Box<Expression>, Operator, Box<Expression>),
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Old => write!(f, "old"),
+            Expression::Constant(value) => write!(f, "{}", value),
+            Expression::Binary(left, operator, right) => {
+                write!(f, "({} {} {})", left, operator.symbol(), right)
+            }
+        }
+    }
+}
+
+impl FromStr for Expression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            chars: s.chars().collect(),
+            position: 0,
+        };
+        let result = parser.sum()?;
+        parser.skip_whitespace();
+        if let Some(c) = parser.peek() {
+            Err(parser.error(format!("unexpected {:?}", c)))?
+        }
+        Ok(result)
+    }
+}
+
+impl Expression {
+    // the exact result, or an error rather than wrapping around or dividing by zero
+    pub fn evaluate(&self, old: u64) -> Result<u64, String> {
+        Ok(match self {
+            Expression::Old => old,
+            Expression::Constant(value) => *value,
+            Expression::Binary(left, operator, right) => {
+                let (l, r) = (left.evaluate(old)?, right.evaluate(old)?);
+                match operator {
+                    Operator::Add => l.checked_add(r),
+                    Operator::Subtract => l.checked_sub(r),
+                    Operator::Multiply => l.checked_mul(r),
+                    Operator::Divide => l.checked_div(r),
+                    Operator::Remainder => l.checked_rem(r),
+                }
+                .ok_or(format!(
+                    "{} {} {} overflows or divides by zero",
+                    l,
+                    operator.symbol(),
+                    r
+                ))?
+            }
+        })
+    }
+
+    // the same without any limit on how big things get
+    pub fn evaluate_big(&self, old: &BigUint) -> Result<BigUint, String> {
+        Ok(match self {
+            Expression::Old => old.clone(),
+            Expression::Constant(value) => BigUint::from(*value),
+            Expression::Binary(left, operator, right) => {
+                let (l, r) = (left.evaluate_big(old)?, right.evaluate_big(old)?);
+                match operator {
+                    Operator::Add => l + r,
+                    Operator::Multiply => l * r,
+                    Operator::Subtract if l >= r => l - r,
+                    Operator::Divide if r != BigUint::ZERO => l / r,
+                    Operator::Remainder if r != BigUint::ZERO => l % r,
+                    _ => Err(format!(
+                        "{} {} {} goes below zero or divides by zero",
+                        l,
+                        operator.symbol(),
+                        r
+                    ))?,
+                }
+            }
+        })
+    }
+}
+
+// recursive descent, with * / % binding tighter than + -
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn error(&self, message: String) -> String {
+        format!("column {}: {}", self.position + 1, message)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn operator(&mut self, operators: &[Operator]) -> Option<Operator> {
+        self.skip_whitespace();
+        let c = self.peek()?;
+        let result = operators.iter().find(|o| o.symbol() == c).copied()?;
+        self.position += 1;
+        Some(result)
+    }
+
+    fn sum(&mut self) -> Result<Expression, String> {
+        let mut result = self.product()?;
+        while let Some(operator) = self.operator(&[Operator::Add, Operator::Subtract]) {
+            result = Expression::Binary(Box::new(result), operator, Box::new(self.product()?));
+        }
+        Ok(result)
+    }
+
+    fn product(&mut self) -> Result<Expression, String> {
+        let mut result = self.term()?;
+        while let Some(operator) =
+            self.operator(&[Operator::Multiply, Operator::Divide, Operator::Remainder])
+        {
+            result = Expression::Binary(Box::new(result), operator, Box::new(self.term()?));
+        }
+        Ok(result)
+    }
+
+    fn term(&mut self) -> Result<Expression, String> {
+        self.skip_whitespace();
+        let start = self.position;
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let result = self.sum()?;
+                self.skip_whitespace();
+                if self.peek() != Some(')') {
+                    Err(self.error(format!("expected ) to match column {}", start + 1)))?
+                }
+                self.position += 1;
+                Ok(result)
+            }
+            Some(c) if c.is_ascii_alphanumeric() => {
+                while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+                    self.position += 1;
+                }
+                let word = self.chars[start..self.position].iter().collect::<String>();
+                if word == "old" {
+                    Ok(Expression::Old)
+                } else {
+                    word.parse().map(Expression::Constant).map_err(|e| {
+                        self.position = start;
+                        self.error(format!("bad number {}: {}", word, e))
+                    })
+                }
+            }
+            Some(c) => Err(self.error(format!("unexpected {:?}", c))),
+            None => Err(self.error("expected a number, old or (".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Expression {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!("(old * 19)", parse("old * 19").to_string());
+        assert_eq!(
+            "((old + (2 * old)) - (old % 3))",
+            parse("old+2*old - old%3").to_string()
+        );
+        assert_eq!(
+            "(((old + 1) * (old - 1)) / 2)",
+            parse(" ( old + 1 ) * (old-1) / 2").to_string()
+        );
+        assert_eq!(
+            "column 9: expected ) to match column 1",
+            "(old + 1".parse::<Expression>().err().unwrap()
+        );
+        assert_eq!(
+            "column 5: unexpected 'x'",
+            "old x 2".parse::<Expression>().err().unwrap()
+        );
+        assert_eq!(
+            "column 7: bad number 2x: invalid digit found in string",
+            "old * 2x".parse::<Expression>().err().unwrap()
+        );
+        assert_eq!(
+            "column 6: expected a number, old or (",
+            "old +".parse::<Expression>().err().unwrap()
+        );
+    }
+
+    #[test]
+    fn checked() -> Result<(), String> {
+        assert_eq!(81, parse("old * old").evaluate(9)?);
+        assert_eq!(2, parse("(old + 3) / 2 % 5").evaluate(11)?);
+        assert!(parse("old - 10").evaluate(9).is_err());
+        assert!(parse("old / (old - 9)").evaluate(9).is_err());
+        assert!(parse("old * old").evaluate(u64::MAX / 2).is_err());
+        let big = BigUint::from(u64::MAX / 2);
+        assert_eq!(
+            &big * &big + 1u32,
+            parse("old * old + 1").evaluate_big(&big)?
+        );
+        assert!(parse("old - 10")
+            .evaluate_big(&BigUint::from(9u32))
+            .is_err());
+        assert!(parse("old % 0").evaluate_big(&big).is_err());
+        Ok(())
+    }
+}
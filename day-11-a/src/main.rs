@@ -1,255 +1,242 @@
+mod cycles;
+mod expression;
+mod modular;
+mod rules;
+mod troop;
+
 use std::{
-    cell::RefCell,
-    collections::HashMap,
+    env,
     error::Error,
     io::{self, BufRead, BufReader},
-    rc::Rc,
 };
 
-#[derive(Debug, Clone, Copy)]
-struct Item(u32);
+use expression::Expression;
+use num_bigint::BigUint;
+use rules::{Predicate, Reduction, Rule, Rules};
+use troop::{Event, Item, Monkey, Troop};
 
-#[derive(Debug, Clone, Copy)]
-enum Operand {
-    Constant(u32),
-    Old,
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut r = BufReader::new(io::stdin());
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+    let (reduction, rounds, args) = parse_flags(&args)?;
+    match args {
+        [] => {
+            do_it(&mut r, reduction, rounds)?;
+        }
+        // finds where each item starts going round in a loop instead of simulating every round
+        ["cycles"] => {
+            extrapolate(&mut r, reduction, rounds)?;
+        }
+        // everything that happens to one item, numbered from 0 in the order they're listed
+        ["journey", item] => {
+            for event in journey(&mut r, reduction, rounds, item.parse()?)?.iter() {
+                println!("{}", event);
+            }
+        }
+        _ => Err(format!("unrecognized arguments: {:?}", args))?,
+    };
+    Ok(())
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Operation {
-    Add(Operand),
-    Multiply(Operand),
+// "--reduce divide=N|modulo|exact" and "--rounds N" before the command
+fn parse_flags<'a, 'b>(
+    args: &'b [&'a str],
+) -> Result<(Reduction, u64, &'b [&'a str]), Box<dyn Error>> {
+    let mut reduction = Reduction::DivideBy(3);
+    let mut rounds = 20;
+    let mut args = args;
+    loop {
+        args = match args {
+            ["--reduce", value, rest @ ..] => {
+                reduction = value.parse()?;
+                rest
+            }
+            ["--rounds", value, rest @ ..] => {
+                rounds = value.parse()?;
+                rest
+            }
+            _ => break,
+        };
+    }
+    Ok((reduction, rounds, args))
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Test(u32);
-
-#[derive(Debug, Clone, Copy)]
-struct Target(u32);
-
-#[derive(Debug)]
-struct Entity {
-    items: Rc<RefCell<Vec<Item>>>,
-    operation: Operation,
-    test: Test,
-    if_true: Target,
-    if_false: Target,
-}
+fn do_it(
+    r: &mut impl std::io::Read,
+    reduction: Reduction,
+    rounds: u64,
+) -> Result<u128, Box<dyn Error>> {
+    let mut troop = Troop::new(parse_monkeys(r)?, reduction)?;
+    println!("relief = {:?}", troop.relief());
 
-impl Entity {
-    fn check_items(&mut self, send_to: impl Fn(Target, Item)) {
-        let mut items = self.items.borrow_mut();
-        for Item(item) in items.iter() {
-            println!("handling {:?}", Item(*item));
-            let result = match self.operation {
-                Operation::Add(operand) => {
-                    item + match operand {
-                        Operand::Constant(value) => value,
-                        Operand::Old => *item,
-                    }
-                }
-                Operation::Multiply(operand) => {
-                    item * match operand {
-                        Operand::Constant(value) => value,
-                        Operand::Old => *item,
-                    }
-                }
-            } / 3;
-            let Test(test) = self.test;
-            let test_result = result % test == 0;
-            println!("{} % {} == 0 ? {}", result, test, test_result);
-            let target = if test_result {
-                self.if_true
-            } else {
-                self.if_false
-            };
-            let result = Item(result);
-            println!("sending {:?} to {:?}", result, target);
-            send_to(target, result);
-        }
-        items.clear();
+    for _ in 0..rounds {
+        troop.round(&mut |event: &Event| println!("{}", event))?;
+        let counts = troop
+            .monkeys()
+            .iter()
+            .map(|monkey| monkey.inspections)
+            .collect::<Vec<_>>();
+        println!("counts = {:?}", counts);
+        println!();
     }
-}
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let mut r = BufReader::new(io::stdin());
-    do_it(&mut r)?;
-    Ok(())
+    let result = troop.monkey_business();
+    println!("result = {}", result);
+    Ok(result)
 }
 
-fn do_it(r: &mut impl std::io::Read) -> Result<usize, Box<dyn Error>> {
-    let entities = Rc::new(RefCell::new(parse_entities(r)?));
+fn extrapolate(
+    r: &mut impl std::io::Read,
+    reduction: Reduction,
+    rounds: u64,
+) -> Result<u128, Box<dyn Error>> {
+    let troop = Troop::new(parse_monkeys(r)?, reduction)?;
+    println!("relief = {:?}", troop.relief());
+    let counts = cycles::inspections(&troop, rounds)?;
+    println!("counts = {:?}", counts);
 
-    let indices = Rc::new(RefCell::new(
-        entities.borrow().keys().map(|i| *i).collect::<Vec<_>>(),
-    ));
-    indices.borrow_mut().sort();
+    let result = troop::monkey_business(counts.into_iter());
+    println!("result = {}", result);
+    Ok(result)
+}
 
-    let debug_entities = {
-        let entities = entities.clone();
-        let indices = indices.clone();
-        move || {
-            for index in indices.borrow().iter() {
-                let entity = entities.borrow().get(index).unwrap().clone();
-                let entity = entity.borrow();
-                println!("entitiy[{}] = {:?}", index, entity);
+fn journey(
+    r: &mut impl std::io::Read,
+    reduction: Reduction,
+    rounds: u64,
+    item: usize,
+) -> Result<Vec<Event>, Box<dyn Error>> {
+    let mut troop = Troop::new(parse_monkeys(r)?, reduction)?;
+    let items = troop.monkeys().iter().map(|m| m.items.len()).sum::<usize>();
+    if item >= items {
+        Err(format!("no item {}, there are only {}", item, items))?
+    }
+    let mut results = Vec::new();
+    for _ in 0..rounds {
+        troop.round(&mut |event: &Event| {
+            if event.item == item {
+                results.push(event.clone());
             }
-        }
-    };
-    debug_entities();
-    println!("");
-
-    let mut counts = HashMap::new();
-    for index in indices.borrow().iter() {
-        counts.insert(*index, 0);
+        })?;
     }
+    Ok(results)
+}
 
-    for round in 0..20 {
-        println!("round {}", round);
-        for index in indices.borrow().iter() {
-            println!("entity {}", index);
-            let entity = entities.borrow().get(index).unwrap().clone();
-            let count = entity.borrow().items.borrow().len();
-            *counts.get_mut(index).unwrap() += count;
-            entity.borrow_mut().check_items(|target, item| {
-                let Target(target) = target;
-                let target = entities.borrow().get(&target).unwrap().clone();
-                target.borrow().items.borrow_mut().push(item);
-            });
+// the lines for one monkey so far
+#[derive(Default)]
+struct Partial {
+    items: Option<Vec<Item>>,
+    operation: Option<Expression>,
+    // the test line waiting for its "If true"
+    test: Option<Predicate>,
+    rules: Vec<Rule>,
+    default: Option<u64>,
+}
+
+impl Partial {
+    fn finish(self, index: usize) -> Result<Monkey, String> {
+        match self {
+            Partial {
+                items: Some(items),
+                operation: Some(operation),
+                test: None,
+                rules,
+                default: Some(default),
+            } => Ok(Monkey {
+                items,
+                operation,
+                rules: Rules { rules, default },
+                inspections: 0,
+            }),
+            _ => Err(format!("monkey {} is missing something", index)),
         }
-        debug_entities();
-        println!("counts = {:?}", counts);
-        println!("");
     }
-
-    let result = {
-        let mut counts = counts.iter().collect::<Vec<_>>();
-        counts.sort_by(|(_, a), (_, b)| b.cmp(a));
-        counts
-            .iter()
-            .take(2)
-            .map(|(_, count)| **count)
-            .reduce(|result, x| result * x)
-            .unwrap()
-    };
-    println!("result = {}", result);
-
-    Ok(result)
 }
 
-fn parse_entities(
-    r: &mut impl std::io::Read,
-) -> Result<HashMap<u32, Rc<RefCell<Entity>>>, Box<dyn Error>> {
+fn parse_monkeys(r: &mut impl std::io::Read) -> Result<Vec<Monkey>, Box<dyn Error>> {
     let header_re = regex::Regex::new(r"^\s*Monkey\s+([0-9]+):\s*$")?;
     let items_re = regex::Regex::new(r"^\s+Starting items:\s*([0-9]+(?:\s*,\s*[0-9]+)*)?\s*$")?;
-    let operation_re =
-        regex::Regex::new(r"^\s*Operation:\s*new\s*=\s*old\s*([+*])\s*([0-9]+|old)\s*$")?;
-    let test_re = regex::Regex::new(r"^\s*Test:\s*divisible\s*by\s*([0-9]+)\s*$")?;
-    let if_re = regex::Regex::new(r"^\s*If\s*(true|false):\s*throw\s*to\s*monkey\s*([0-9]+)\s*$")?;
+    let operation_re = regex::Regex::new(r"^\s*Operation:\s*new\s*=(.*)$")?;
+    let test_re = regex::Regex::new(r"^\s*Test:(.*)$")?;
+    let if_re =
+        regex::Regex::new(r"^\s*(?:If\s+(.+?)|Otherwise):\s*throw\s*to\s*monkey\s*([0-9]+)\s*$")?;
 
-    let current_index = RefCell::new(None);
-    let current_items = RefCell::<Option<Rc<RefCell<Vec<Item>>>>>::new(None);
-    let current_operation = RefCell::new(None);
-    let current_test = RefCell::new(None);
-    let current_if_true = RefCell::new(None);
-    let current_if_false = RefCell::new(None);
-    let mut results = HashMap::new();
-    let mut handle_current_entity = || {
-        let current_index = current_index.borrow();
-        let current_items = current_items.borrow();
-        let current_operation = current_operation.borrow();
-        let current_test = current_test.borrow();
-        let current_if_true = current_if_true.borrow();
-        let current_if_false = current_if_false.borrow();
-        if current_index.is_some()
-            && current_items.is_some()
-            && current_operation.is_some()
-            && current_test.is_some()
-            && current_if_true.is_some()
-            && current_if_false.is_some()
-        {
-            results.insert(
-                current_index.unwrap(),
-                Rc::new(RefCell::new(Entity {
-                    items: current_items.clone().unwrap(),
-                    operation: current_operation.unwrap(),
-                    test: current_test.unwrap(),
-                    if_true: current_if_true.unwrap(),
-                    if_false: current_if_false.unwrap(),
-                })),
-            );
-            Ok(())
-        } else if current_index.is_none()
-            && current_items.is_none()
-            && current_operation.is_none()
-            && current_test.is_none()
-            && current_if_true.is_none()
-            && current_if_false.is_none()
-        {
-            // nothing to do, no item
-            Ok(())
-        } else {
-            Err("partial item")
-        }
-    };
-    for line in BufReader::new(r).lines() {
+    let mut results = Vec::new();
+    let mut current = None;
+    let mut next_item = 0;
+    for (i, line) in BufReader::new(r).lines().enumerate() {
         let line = line?;
         println!("line = {}", line);
+        let error = |message: &str| format!("line {}: {}: {}", i + 1, message, line);
 
         if line.trim().is_empty() {
             continue;
-        } else if let Some(captures) = header_re.captures(line.as_str()) {
-            // process any previous entitiy that might now be finished
-            handle_current_entity()?;
-            let index = captures[1].parse::<u32>()?;
+        }
+        if let Some(captures) = header_re.captures(line.as_str()) {
+            // process any previous monkey that might now be finished
+            if let Some(partial) = current.take() {
+                results.push(Partial::finish(partial, results.len())?);
+            }
+            // monkeys throw to each other by index, so they have to be listed in order
+            let index = captures[1].parse::<usize>()?;
+            if index != results.len() {
+                Err(error(&format!("expected monkey {}", results.len())))?
+            }
             println!("header, index = {}", index);
-            current_index.replace(Some(index));
-        } else if let Some(captures) = items_re.captures(line.as_str()) {
-            let items = &captures[1];
-            let items = Rc::new(RefCell::new(
-                items
-                    .split(",")
-                    .map(|item| -> Result<Item, Box<dyn Error>> {
-                        Ok(Item(item.trim().parse::<u32>()?))
-                    })
-                    .collect::<Result<Vec<_>, _>>()?,
-            ));
+            current = Some(Partial::default());
+            continue;
+        }
+        let partial = current
+            .as_mut()
+            .ok_or(error("expected a Monkey line first"))?;
+        if let Some(captures) = items_re.captures(line.as_str()) {
+            let mut items = Vec::new();
+            for worry in captures.get(1).map_or("", |c| c.as_str()).split(',') {
+                if worry.trim().is_empty() {
+                    continue;
+                }
+                items.push(Item {
+                    id: next_item,
+                    worry: worry.trim().parse::<BigUint>()?,
+                });
+                next_item += 1;
+            }
             println!("items = {:?}", items);
-            current_items.replace(Some(items));
+            partial.items = Some(items);
         } else if let Some(captures) = operation_re.captures(line.as_str()) {
-            let operator = &captures[1];
-            let operand = if &captures[2] == "old" {
-                Operand::Old
-            } else {
-                Operand::Constant(captures[2].parse()?)
-            };
-            let operation = match operator {
-                "+" => Operation::Add(operand),
-                "*" => Operation::Multiply(operand),
-                _ => Err(format!("unrecognized operand: {:?}", operand))?,
-            };
-            println!("operation = {:?}", operation);
-            current_operation.replace(Some(operation));
+            let operation = captures[1].parse::<Expression>()?;
+            println!("operation = {}", operation);
+            partial.operation = Some(operation);
         } else if let Some(captures) = test_re.captures(line.as_str()) {
-            let test = Test(captures[1].parse::<u32>()?);
-            println!("test = {:?}", test);
-            current_test.replace(Some(test));
+            let test = captures[1].parse::<Predicate>()?;
+            println!("test = {}", test);
+            partial.test = Some(test);
         } else if let Some(captures) = if_re.captures(line.as_str()) {
-            let condition = &captures[1];
-            let target = Target(captures[2].parse::<u32>()?);
-            println!("if, condition = {}, target = {:?}", condition, target);
+            let condition = captures.get(1).map(|c| c.as_str());
+            let target = captures[2].parse::<u64>()?;
+            println!("if, condition = {:?}, target = {}", condition, target);
             match condition {
-                "true" => current_if_true.replace(Some(target)),
-                "false" => current_if_false.replace(Some(target)),
-                _ => Err(format!("unrecognized condition: {}", condition))?,
+                // the original format, where true goes with the test line before it
+                Some("true") => {
+                    let predicate = partial.test.take().ok_or(error("no test before"))?;
+                    partial.rules.push(Rule { predicate, target });
+                }
+                Some("false") | None => partial.default = Some(target),
+                Some(condition) => {
+                    let predicate = condition.parse()?;
+                    partial.rules.push(Rule { predicate, target });
+                }
             };
         } else {
-            Err(format!("unrecognized line = {}", line))?;
+            Err(error("unrecognized line"))?;
         }
     }
-    // done with all lines, process any entitiy at the end
-    handle_current_entity()?;
-    println!("");
+    // done with all lines, process any monkey at the end
+    if let Some(partial) = current.take() {
+        results.push(Partial::finish(partial, results.len())?);
+    }
+    println!();
     Ok(results)
 }
 
@@ -257,12 +244,7 @@ fn parse_entities(
 mod tests {
     use super::*;
 
-    #[test]
-    fn sample() -> Result<(), Box<dyn Error>> {
-        assert_eq!(
-            10605,
-            do_it(
-                &mut r"Monkey 0:
+    const SAMPLE: &str = r"Monkey 0:
   Starting items: 79, 98
   Operation: new = old * 19
   Test: divisible by 23
@@ -288,10 +270,80 @@ Monkey 3:
   Operation: new = old + 3
   Test: divisible by 17
     If true: throw to monkey 0
-    If false: throw to monkey 1"
-                    .as_bytes(),
-            )?
+    If false: throw to monkey 1";
+
+    #[test]
+    fn sample() -> Result<(), Box<dyn Error>> {
+        assert_eq!(
+            10605,
+            do_it(&mut SAMPLE.as_bytes(), Reduction::DivideBy(3), 20)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reductions_agree() -> Result<(), Box<dyn Error>> {
+        // part b's answer after 20 rounds
+        assert_eq!(10197, do_it(&mut SAMPLE.as_bytes(), Reduction::Modulo, 20)?);
+        assert_eq!(10197, do_it(&mut SAMPLE.as_bytes(), Reduction::Exact, 20)?);
+        assert_eq!(
+            10197,
+            extrapolate(&mut SAMPLE.as_bytes(), Reduction::Modulo, 20)?
+        );
+        assert!(extrapolate(&mut SAMPLE.as_bytes(), Reduction::DivideBy(3), 20).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rule_lines() -> Result<(), Box<dyn Error>> {
+        let monkeys = parse_monkeys(
+            &mut r"Monkey 0:
+  Starting items: 1, 2
+  Operation: new = (old + 1) * old
+  If greater than 10: throw to monkey 1
+  If modulo 5 in 0..2: throw to monkey 2
+  Otherwise: throw to monkey 0"
+                .as_bytes(),
+        )?;
+        assert_eq!(2, monkeys[0].rules.rules.len());
+        assert_eq!(0, monkeys[0].rules.default);
+        assert_eq!(
+            Predicate::ModuloRange(5, 0, 2),
+            monkeys[0].rules.rules[1].predicate
+        );
+        assert_eq!(
+            vec![0, 1],
+            monkeys[0].items.iter().map(|i| i.id).collect::<Vec<_>>()
+        );
+        assert!(parse_monkeys(&mut "Monkey 0:\n  If true: throw to monkey 1".as_bytes()).is_err());
+        assert_eq!(
+            "line 1: expected monkey 0: Monkey 1:",
+            parse_monkeys(&mut "Monkey 1:".as_bytes())
+                .err()
+                .unwrap()
+                .to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn item_journey() -> Result<(), Box<dyn Error>> {
+        let events = journey(&mut SAMPLE.as_bytes(), Reduction::DivideBy(3), 1, 0)?;
+        let events = events.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "round 1, monkey 0, item 0: inspects it at 79",
+                "round 1, monkey 0, item 0: worry is now 500",
+                "round 1, monkey 0, item 0: no tests match",
+                "round 1, monkey 0, item 0: throws it to monkey 3",
+                "round 1, monkey 3, item 0: inspects it at 500",
+                "round 1, monkey 3, item 0: worry is now 167",
+                "round 1, monkey 3, item 0: no tests match",
+                "round 1, monkey 3, item 0: throws it to monkey 1",
+            ],
+            events
         );
+        assert!(journey(&mut SAMPLE.as_bytes(), Reduction::DivideBy(3), 1, 10).is_err());
         Ok(())
     }
 }
@@ -0,0 +1,171 @@
+use crate::expression::{Expression, Operator};
+
+// working operations out modulo something, so worry levels never have to get any bigger
+impl Expression {
+    // what old has to be known modulo for evaluate_mod to get the result right modulo n, or none
+    // if nothing will do because something is divided by an amount that depends on old, or
+    // something subtracted might take it below zero
+    pub fn required_modulus(&self, n: u64) -> Option<u64> {
+        match self {
+            Expression::Old => Some(n),
+            Expression::Constant(_) => Some(1),
+            Expression::Binary(_, Operator::Subtract, _) if self.lowest().is_none() => None,
+            Expression::Binary(left, operator, right) => match operator {
+                Operator::Add | Operator::Subtract | Operator::Multiply => {
+                    lcm(left.required_modulus(n)?, right.required_modulus(n)?)
+                }
+                // floor(x / k) mod n only depends on x mod k*n
+                Operator::Divide => left.required_modulus(right.constant()?.checked_mul(n)?),
+                // x % k only depends on x mod k
+                Operator::Remainder => left.required_modulus(right.constant()?),
+            },
+        }
+    }
+
+    // the result modulo n, where old only has to be right modulo required_modulus(n)
+    pub fn evaluate_mod(&self, old: u64, n: u64) -> Result<u64, String> {
+        if n == 0 {
+            Err("can't reduce modulo 0")?
+        }
+        Ok(match self {
+            // a residue can't tell whether the real value went below zero, which evaluate catches,
+            // so only subtractions that never can are allowed
+            Expression::Binary(_, Operator::Subtract, _) if self.lowest().is_none() => {
+                Err(format!(
+                    "{} could go below zero, so it can't be reduced modulo {}",
+                    self, n
+                ))?
+            }
+            Expression::Old => old % n,
+            Expression::Constant(value) => value % n,
+            Expression::Binary(left, operator, right) => match operator {
+                Operator::Add | Operator::Subtract | Operator::Multiply => {
+                    let l = left.evaluate_mod(old, n)? as u128;
+                    let r = right.evaluate_mod(old, n)? as u128;
+                    let n = n as u128;
+                    (match operator {
+                        Operator::Add => (l + r) % n,
+                        Operator::Subtract => (l + n - r) % n,
+                        _ => l * r % n,
+                    }) as u64
+                }
+                Operator::Divide | Operator::Remainder => {
+                    let k = right
+                        .constant()
+                        .ok_or(format!("can't reduce {} modulo {}", self, n))?;
+                    if k == 0 {
+                        Err(format!("{} divides by zero", self))?
+                    }
+                    if *operator == Operator::Divide {
+                        let kn = k
+                            .checked_mul(n)
+                            .ok_or(format!("can't reduce {} modulo {}", self, n))?;
+                        left.evaluate_mod(old, kn)? / k % n
+                    } else {
+                        left.evaluate_mod(old, k)? % n
+                    }
+                }
+            },
+        })
+    }
+
+    // the smallest this can be for any old, or none if a subtraction in it could go below zero
+    // or it divides by something that depends on old
+    fn lowest(&self) -> Option<u64> {
+        match self {
+            Expression::Old => Some(0),
+            Expression::Constant(value) => Some(*value),
+            Expression::Binary(left, operator, right) => {
+                let l = left.lowest()?;
+                match operator {
+                    Operator::Add => Some(l.saturating_add(right.lowest()?)),
+                    Operator::Multiply => Some(l.saturating_mul(right.lowest()?)),
+                    Operator::Subtract => l.checked_sub(right.constant()?),
+                    Operator::Divide => l.checked_div(right.constant()?),
+                    Operator::Remainder => right.constant().map(|_| 0),
+                }
+            }
+        }
+    }
+
+    // the value of anything that doesn't involve old
+    fn constant(&self) -> Option<u64> {
+        match self {
+            Expression::Old => None,
+            Expression::Constant(value) => Some(*value),
+            Expression::Binary(left, _, right) => {
+                left.constant()?;
+                right.constant()?;
+                self.evaluate(0).ok()
+            }
+        }
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+pub fn lcm(a: u64, b: u64) -> Option<u64> {
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Expression {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn reduced_matches_exact() -> Result<(), String> {
+        for (s, n) in [
+            ("old * old + 7", 23 * 19),
+            ("old * 19 + 30 - 7", 13),
+            ("(old + 5) / 3", 17),
+            ("old % 10 * old / 4", 6),
+            ("(old * old) / (2 + 1)", 7),
+        ] {
+            let e = parse(s);
+            let modulus = e.required_modulus(n).unwrap();
+            for old in 0..1000 {
+                assert_eq!(
+                    e.evaluate(old)? % n,
+                    e.evaluate_mod(old % modulus, n)?,
+                    "{} with old = {} mod {}",
+                    s,
+                    old,
+                    n
+                );
+            }
+        }
+        assert_eq!(None, parse("100 / old").required_modulus(7));
+        assert!(parse("100 % old").evaluate_mod(5, 7).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn subtraction_never_wraps() {
+        // exactly this goes below zero whenever old is under 10, which reduced can't tell
+        let e = parse("old - 10");
+        assert!(e.evaluate(9).is_err());
+        assert!(e.evaluate_big(&9u32.into()).is_err());
+        assert_eq!(None, e.required_modulus(7));
+        assert_eq!(
+            "(old - 10) could go below zero, so it can't be reduced modulo 7",
+            e.evaluate_mod(9, 7).err().unwrap()
+        );
+        assert_eq!(
+            "(old - 10) could go below zero, so it can't be reduced modulo 7",
+            e.evaluate_mod(20, 7).err().unwrap()
+        );
+        // never below zero, but only an exact answer can show that
+        assert_eq!(None, parse("old * 19 - old").required_modulus(13));
+        assert_eq!(Some(14), parse("(old + 10) / 2 - 5").required_modulus(7));
+    }
+}
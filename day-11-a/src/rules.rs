@@ -0,0 +1,284 @@
+use std::{fmt, str::FromStr};
+
+use num_bigint::BigUint;
+
+use crate::{expression::Expression, modular::lcm};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Predicate {
+    DivisibleBy(u64),
+    GreaterThan(u64),
+    EqualTo(u64),
+    // the worry level modulo the first number is somewhere in min..=max
+    ModuloRange(u64, u64, u64),
+}
+
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Predicate::DivisibleBy(n) => write!(f, "divisible by {}", n),
+            Predicate::GreaterThan(n) => write!(f, "greater than {}", n),
+            Predicate::EqualTo(n) => write!(f, "equal to {}", n),
+            Predicate::ModuloRange(n, min, max) => write!(f, "modulo {} in {}..{}", n, min, max),
+        }
+    }
+}
+
+impl FromStr for Predicate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        let number = |s: &str| {
+            s.parse::<u64>()
+                .map_err(|e| format!("bad number {} in {:?}: {}", s, words.join(" "), e))
+        };
+        let result = match words.as_slice() {
+            ["divisible", "by", n] => Predicate::DivisibleBy(number(n)?),
+            ["greater", "than", n] => Predicate::GreaterThan(number(n)?),
+            ["equal", "to", n] => Predicate::EqualTo(number(n)?),
+            ["modulo", n, "in", range] => {
+                let (min, max) = range
+                    .split_once("..")
+                    .ok_or(format!("expected a range like 1..3 but got {}", range))?;
+                Predicate::ModuloRange(number(n)?, number(min)?, number(max)?)
+            }
+            _ => Err(format!("unrecognized test: {}", s))?,
+        };
+        match result {
+            Predicate::DivisibleBy(0) | Predicate::ModuloRange(0, _, _) => {
+                Err(format!("can't test {}", result))?
+            }
+            _ => Ok(result),
+        }
+    }
+}
+
+impl Predicate {
+    pub fn test(&self, worry: &BigUint) -> bool {
+        match self {
+            Predicate::DivisibleBy(n) => worry % n == BigUint::ZERO,
+            Predicate::GreaterThan(n) => *worry > BigUint::from(*n),
+            Predicate::EqualTo(n) => *worry == BigUint::from(*n),
+            Predicate::ModuloRange(n, min, max) => {
+                let m = worry % n;
+                m >= BigUint::from(*min) && m <= BigUint::from(*max)
+            }
+        }
+    }
+
+    // anything that only depends on the worry level modulo some number, which is what that is
+    fn modulus(&self) -> Option<u64> {
+        match self {
+            Predicate::DivisibleBy(n) | Predicate::ModuloRange(n, _, _) => Some(*n),
+            Predicate::GreaterThan(_) | Predicate::EqualTo(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    pub predicate: Predicate,
+    pub target: u64,
+}
+
+// the first rule that matches picks who the item goes to, or the default if none do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rules {
+    pub rules: Vec<Rule>,
+    pub default: u64,
+}
+
+impl Rules {
+    // which rule matched, if any, and who to throw to
+    pub fn target(&self, worry: &BigUint) -> (Option<&Rule>, u64) {
+        match self.rules.iter().find(|rule| rule.predicate.test(worry)) {
+            Some(rule) => (Some(rule), rule.target),
+            None => (None, self.default),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reduction {
+    // divide by this after every inspection, rounding down
+    DivideBy(u64),
+    // only keep worry levels modulo the least common multiple of every test's modulus
+    Modulo,
+    // keep the whole number, however big it gets
+    Exact,
+}
+
+impl FromStr for Reduction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some(("divide", n)) => match n.parse::<u64>() {
+                Ok(0) => Err("can't divide by 0".to_string()),
+                Ok(n) => Ok(Reduction::DivideBy(n)),
+                Err(e) => Err(format!("bad number {}: {}", n, e)),
+            },
+            None if s == "modulo" => Ok(Reduction::Modulo),
+            None if s == "exact" => Ok(Reduction::Exact),
+            _ => Err(format!("unrecognized reduction: {}", s)),
+        }
+    }
+}
+
+// how worry levels change when a monkey inspects an item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Relief {
+    pub reduction: Reduction,
+    // only used when reducing modulo something
+    pub modulus: u64,
+}
+
+impl Relief {
+    // checks that the reduction keeps every monkey's operation and rules working
+    pub fn new(reduction: Reduction, monkeys: &[(&Expression, &Rules)]) -> Result<Relief, String> {
+        if reduction != Reduction::Modulo {
+            return Ok(Relief {
+                reduction,
+                modulus: 1,
+            });
+        }
+        let mut modulus = 1u64;
+        for (_, rules) in monkeys.iter() {
+            for rule in rules.rules.iter() {
+                let n = rule
+                    .predicate
+                    .modulus()
+                    .ok_or(format!("{} doesn't work modulo anything", rule.predicate))?;
+                modulus = lcm(modulus, n).ok_or("the tests' least common multiple is too big")?;
+            }
+        }
+        // operations can need more than the tests do, and then need more again for that, so keep
+        // going until nothing changes
+        loop {
+            let mut next = modulus;
+            for (operation, _) in monkeys.iter() {
+                next = operation
+                    .required_modulus(next)
+                    .and_then(|required| lcm(next, required))
+                    .ok_or(format!("{} can't be worked out modulo anything", operation))?;
+            }
+            if next == modulus {
+                break;
+            }
+            modulus = next;
+        }
+        Ok(Relief { reduction, modulus })
+    }
+
+    pub fn apply(&self, operation: &Expression, worry: &BigUint) -> Result<BigUint, String> {
+        Ok(match self.reduction {
+            Reduction::DivideBy(n) => {
+                let worry = u64::try_from(worry).map_err(|e| e.to_string())?;
+                BigUint::from(operation.evaluate(worry)? / n)
+            }
+            Reduction::Modulo => {
+                let worry = u64::try_from(worry % self.modulus).unwrap();
+                BigUint::from(operation.evaluate_mod(worry, self.modulus)?)
+            }
+            Reduction::Exact => operation.evaluate_big(worry)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> Rules {
+        Rules {
+            rules: vec![
+                Rule {
+                    predicate: "greater than 100".parse().unwrap(),
+                    target: 1,
+                },
+                Rule {
+                    predicate: "equal to 42".parse().unwrap(),
+                    target: 2,
+                },
+                Rule {
+                    predicate: "divisible by 7".parse().unwrap(),
+                    target: 3,
+                },
+                Rule {
+                    predicate: "modulo 10 in 3..5".parse().unwrap(),
+                    target: 4,
+                },
+            ],
+            default: 0,
+        }
+    }
+
+    #[test]
+    fn first_match_wins() {
+        let rules = rules();
+        let target = |worry: u64| rules.target(&BigUint::from(worry)).1;
+        assert_eq!(1, target(140));
+        assert_eq!(2, target(42));
+        assert_eq!(3, target(49));
+        assert_eq!(4, target(13));
+        assert_eq!(4, target(35 - 20));
+        assert_eq!(0, target(16));
+        assert_eq!(None, rules.target(&BigUint::from(16u32)).0);
+        assert!("divisible by 0".parse::<Predicate>().is_err());
+        assert!("modulo 10 in 3".parse::<Predicate>().is_err());
+        assert!("smaller than 3".parse::<Predicate>().is_err());
+    }
+
+    #[test]
+    fn reductions() -> Result<(), String> {
+        assert_eq!(Reduction::DivideBy(3), "divide=3".parse()?);
+        assert_eq!(Reduction::Exact, "exact".parse()?);
+        assert!("divide=0".parse::<Reduction>().is_err());
+        assert!("modulo=3".parse::<Reduction>().is_err());
+
+        let operation = "old * old + 3".parse::<Expression>()?;
+        let divisible = Rules {
+            rules: vec![Rule {
+                predicate: Predicate::DivisibleBy(7),
+                target: 1,
+            }],
+            default: 0,
+        };
+        let relief = Relief::new(Reduction::Modulo, &[(&operation, &divisible)])?;
+        assert_eq!(7, relief.modulus);
+        assert_eq!(
+            BigUint::from(0u32),
+            relief.apply(&operation, &BigUint::from(9u32))?
+        );
+        let relief = Relief::new(Reduction::DivideBy(3), &[(&operation, &divisible)])?;
+        assert_eq!(
+            BigUint::from(28u32),
+            relief.apply(&operation, &BigUint::from(9u32))?
+        );
+        let relief = Relief::new(Reduction::Exact, &[(&operation, &divisible)])?;
+        assert_eq!(
+            BigUint::from(84u32),
+            relief.apply(&operation, &BigUint::from(9u32))?
+        );
+
+        // only as much as the tests and operations need, rather than the product
+        let quarters = "old * 3 % 4".parse::<Expression>()?;
+        let by = |n: u64| Rules {
+            rules: vec![Rule {
+                predicate: Predicate::DivisibleBy(n),
+                target: 1,
+            }],
+            default: 0,
+        };
+        let (six, ten) = (by(6), by(10));
+        let relief = Relief::new(Reduction::Modulo, &[(&operation, &six), (&quarters, &ten)])?;
+        assert_eq!(60, relief.modulus);
+
+        // comparisons don't survive being reduced
+        assert!(Relief::new(Reduction::Modulo, &[(&operation, &rules())]).is_err());
+        let operation = "1000 / old".parse::<Expression>()?;
+        assert!(Relief::new(Reduction::Modulo, &[(&operation, &divisible)]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+use std::{fmt, mem};
+
+use num_bigint::BigUint;
+
+use crate::{
+    expression::Expression,
+    rules::{Predicate, Reduction, Relief, Rules},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    // numbered in the order they're listed in the input, so they can be followed around
+    pub id: usize,
+    pub worry: BigUint,
+}
+
+#[derive(Debug, Clone)]
+pub struct Monkey {
+    pub items: Vec<Item>,
+    pub operation: Expression,
+    pub rules: Rules,
+    pub inspections: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventKind {
+    // the worry level before the monkey looks at it
+    Inspect(BigUint),
+    // after the operation and any relief
+    NewWorry(BigUint),
+    // the test that matched, if any
+    Test(Option<Predicate>),
+    Throw(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    // starting from 1
+    pub round: u64,
+    pub monkey: usize,
+    pub item: usize,
+    pub kind: EventKind,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "round {}, monkey {}, item {}: ",
+            self.round, self.monkey, self.item
+        )?;
+        match &self.kind {
+            EventKind::Inspect(worry) => write!(f, "inspects it at {}", worry),
+            EventKind::NewWorry(worry) => write!(f, "worry is now {}", worry),
+            EventKind::Test(Some(predicate)) => write!(f, "it's {}", predicate),
+            EventKind::Test(None) => write!(f, "no tests match"),
+            EventKind::Throw(target) => write!(f, "throws it to monkey {}", target),
+        }
+    }
+}
+
+pub struct Troop {
+    monkeys: Vec<Monkey>,
+    relief: Relief,
+    round: u64,
+}
+
+impl Troop {
+    // monkeys take their turns in order and throw to each other by index
+    pub fn new(monkeys: Vec<Monkey>, reduction: Reduction) -> Result<Troop, String> {
+        for (i, monkey) in monkeys.iter().enumerate() {
+            let targets = monkey.rules.rules.iter().map(|rule| rule.target);
+            for target in targets.chain([monkey.rules.default]) {
+                if target as usize >= monkeys.len() {
+                    Err(format!(
+                        "monkey {} throws to monkey {}, but there are only {}",
+                        i,
+                        target,
+                        monkeys.len()
+                    ))?
+                }
+            }
+        }
+        let relief = {
+            let pairs = monkeys
+                .iter()
+                .map(|monkey| (&monkey.operation, &monkey.rules))
+                .collect::<Vec<_>>();
+            Relief::new(reduction, &pairs)?
+        };
+        Ok(Troop {
+            monkeys,
+            relief,
+            round: 0,
+        })
+    }
+
+    pub fn monkeys(&self) -> &[Monkey] {
+        &self.monkeys
+    }
+
+    pub fn relief(&self) -> &Relief {
+        &self.relief
+    }
+
+    // every monkey takes one turn, telling the observer everything that happens as it happens
+    pub fn round(&mut self, observer: &mut impl FnMut(&Event)) -> Result<(), String> {
+        self.round += 1;
+        for i in 0..self.monkeys.len() {
+            // anything a monkey throws to itself waits for its next turn
+            let items = mem::take(&mut self.monkeys[i].items);
+            for item in items.into_iter() {
+                let monkey = &mut self.monkeys[i];
+                let mut event = |kind| {
+                    observer(&Event {
+                        round: self.round,
+                        monkey: i,
+                        item: item.id,
+                        kind,
+                    })
+                };
+                event(EventKind::Inspect(item.worry.clone()));
+                monkey.inspections += 1;
+                let worry = self.relief.apply(&monkey.operation, &item.worry)?;
+                event(EventKind::NewWorry(worry.clone()));
+                let (rule, target) = monkey.rules.target(&worry);
+                event(EventKind::Test(rule.map(|rule| rule.predicate)));
+                event(EventKind::Throw(target as usize));
+                self.monkeys[target as usize]
+                    .items
+                    .push(Item { id: item.id, worry });
+            }
+        }
+        Ok(())
+    }
+
+    // the two busiest monkeys' inspections multiplied together
+    pub fn monkey_business(&self) -> u128 {
+        monkey_business(self.monkeys.iter().map(|m| m.inspections as u128))
+    }
+}
+
+pub fn monkey_business(counts: impl Iterator<Item = u128>) -> u128 {
+    let mut counts = counts.collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.cmp(a));
+    counts.iter().take(2).product()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::rules::Rule;
+
+    fn monkey(worries: &[(usize, u32)], operation: &str, rules: Vec<Rule>, default: u64) -> Monkey {
+        Monkey {
+            items: worries
+                .iter()
+                .map(|(id, worry)| Item {
+                    id: *id,
+                    worry: BigUint::from(*worry),
+                })
+                .collect(),
+            operation: operation.parse().unwrap(),
+            rules: Rules { rules, default },
+            inspections: 0,
+        }
+    }
+
+    #[test]
+    fn events() -> Result<(), String> {
+        let mut troop = Troop::new(
+            vec![
+                monkey(
+                    &[(0, 4), (1, 5)],
+                    "old * 3",
+                    vec![Rule {
+                        predicate: Predicate::DivisibleBy(2),
+                        target: 1,
+                    }],
+                    0,
+                ),
+                monkey(&[], "old + 1", vec![], 0),
+            ],
+            Reduction::DivideBy(2),
+        )?;
+        let mut events = Vec::new();
+        troop.round(&mut |e: &Event| events.push(e.to_string()))?;
+        assert_eq!(
+            vec![
+                "round 1, monkey 0, item 0: inspects it at 4",
+                "round 1, monkey 0, item 0: worry is now 6",
+                "round 1, monkey 0, item 0: it's divisible by 2",
+                "round 1, monkey 0, item 0: throws it to monkey 1",
+                "round 1, monkey 0, item 1: inspects it at 5",
+                "round 1, monkey 0, item 1: worry is now 7",
+                "round 1, monkey 0, item 1: no tests match",
+                "round 1, monkey 0, item 1: throws it to monkey 0",
+                "round 1, monkey 1, item 0: inspects it at 6",
+                "round 1, monkey 1, item 0: worry is now 3",
+                "round 1, monkey 1, item 0: no tests match",
+                "round 1, monkey 1, item 0: throws it to monkey 0",
+            ],
+            events
+        );
+        assert_eq!(
+            vec![1, 0],
+            troop.monkeys()[0]
+                .items
+                .iter()
+                .map(|item| item.id)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(2, troop.monkey_business());
+        Ok(())
+    }
+
+    #[test]
+    fn bad_targets() {
+        assert_eq!(
+            "monkey 1 throws to monkey 2, but there are only 2",
+            Troop::new(
+                vec![monkey(&[], "old", vec![], 1), monkey(&[], "old", vec![], 2)],
+                Reduction::Exact
+            )
+            .err()
+            .unwrap()
+        );
+    }
+}
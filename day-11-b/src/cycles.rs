@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use num_bigint::BigUint;
+
+use crate::{rules::Reduction, troop::Troop};
+
+// how many items each monkey inspects over the given number of rounds
+//
+// items never affect each other, and once worry levels are reduced modulo something each item can
+// only be in so many states at the start of a round, so each one has to end up going round in a
+// loop, and only needs simulating until it does
+pub fn inspections(troop: &Troop, rounds: u64) -> Result<Vec<u128>, String> {
+    if troop.relief().reduction != Reduction::Modulo {
+        Err("items only go round in loops when reducing modulo something")?
+    }
+    let modulus = troop.relief().modulus;
+    let monkeys = troop.monkeys();
+
+    let mut counts = vec![0u128; monkeys.len()];
+    let items = monkeys
+        .iter()
+        .enumerate()
+        .flat_map(|(i, monkey)| monkey.items.iter().map(move |item| (i, &item.worry)));
+    for (monkey, worry) in items {
+        let worry = u64::try_from(worry % modulus).unwrap();
+        // each round's starting state, which monkeys inspected the item that round, and when each
+        // state was first seen
+        let mut state = (monkey, worry);
+        let mut history = Vec::<Vec<usize>>::new();
+        let mut seen = HashMap::new();
+        let mut round = 0;
+        while round < rounds {
+            if let Some(start) = seen.get(&state) {
+                let length = round - start;
+                let remaining = rounds - round;
+                for (i, visits) in history[*start as usize..].iter().enumerate() {
+                    // every full loop, and the part of one left over at the end
+                    let times = remaining / length + u64::from((i as u64) < remaining % length);
+                    for m in visits.iter() {
+                        counts[*m] += times as u128;
+                    }
+                }
+                break;
+            }
+            seen.insert(state, round);
+
+            // a monkey who hasn't had their turn yet this round gets to throw it again straight away
+            let (mut monkey, mut worry) = state;
+            let mut visits = Vec::new();
+            loop {
+                visits.push(monkey);
+                worry = monkeys[monkey].operation.evaluate_mod(worry, modulus)?;
+                let target = monkeys[monkey].rules.target(&BigUint::from(worry)).1 as usize;
+                let passed = target <= monkey;
+                monkey = target;
+                if passed {
+                    break;
+                }
+            }
+            for m in visits.iter() {
+                counts[*m] += 1;
+            }
+            history.push(visits);
+            state = (monkey, worry);
+            round += 1;
+        }
+    }
+    Ok(counts)
+}
@@ -0,0 +1,150 @@
+use crate::{Grid, Location, Point};
+
+// what counts as a legal step, and what each one costs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Climbing {
+    // the most a single step can go up
+    pub max_up: u32,
+    // the most a single step can go down, if there's any limit at all
+    pub max_down: Option<u32>,
+    pub diagonal: bool,
+    // added to the 1 every step costs, for each unit of height it goes up or down
+    pub cost_per_height: u32,
+}
+
+impl Default for Climbing {
+    // the puzzle's rules: one up at a time, any distance down, and every step costs the same
+    fn default() -> Self {
+        Self {
+            max_up: 1,
+            max_down: None,
+            diagonal: false,
+            cost_per_height: 0,
+        }
+    }
+}
+
+impl Climbing {
+    pub fn can_go(&self, from: &Location, to: &Location) -> bool {
+        if to.height() > from.height() {
+            to.height() - from.height() <= self.max_up
+        } else {
+            self.max_down
+                .is_none_or(|max_down| from.height() - to.height() <= max_down)
+        }
+    }
+
+    pub fn cost(&self, from: &Location, to: &Location) -> u32 {
+        1 + self.cost_per_height * from.height().abs_diff(to.height())
+    }
+
+    // everywhere that can be reached in one step from p, and what it costs
+    pub fn moves(&self, heightmap: &Grid<Location>, p: Point) -> Vec<(Point, u32)> {
+        let mut neighbors = heightmap.get_neighbors(p);
+        if self.diagonal {
+            neighbors.extend(heightmap.get_diagonal_neighbors(p));
+        }
+        let from = heightmap.get(p).unwrap();
+        neighbors
+            .into_iter()
+            .filter_map(|next| {
+                let to = heightmap.get(next).unwrap();
+                if self.can_go(&from, &to) {
+                    Some((next, self.cost(&from, &to)))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    // never more than it really costs to get from p to target, so a* can use it
+    pub fn estimate(&self, heightmap: &Grid<Location>, p: Point, target: Point) -> u32 {
+        let steps = if self.diagonal {
+            p.x().abs_diff(target.x()).max(p.y().abs_diff(target.y()))
+        } else {
+            p.distance(target)
+        };
+        // it has to climb or descend the difference one way or another
+        let height = heightmap.get(p).unwrap().height();
+        let climb = height.abs_diff(heightmap.get(target).unwrap().height());
+        steps + self.cost_per_height * climb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Size;
+
+    // a 3 x 3 heightmap, row by row
+    fn heightmap(heights: [u32; 9]) -> Grid<Location> {
+        let mut result = Grid::new();
+        result.set_size(Size::new(3, 3), Location::new(0));
+        for (i, height) in heights.iter().enumerate() {
+            let p = Point::new(i as u32 % 3, i as u32 / 3);
+            result.set(p, Location::new(*height)).unwrap();
+        }
+        result
+    }
+
+    fn destinations(climbing: Climbing, heightmap: &Grid<Location>) -> Vec<(u32, u32, u32)> {
+        let mut result = climbing
+            .moves(heightmap, Point::new(1, 1))
+            .into_iter()
+            .map(|(p, cost)| (p.x(), p.y(), cost))
+            .collect::<Vec<_>>();
+        result.sort();
+        result
+    }
+
+    #[test]
+    fn rules() {
+        let heightmap = heightmap([9, 6, 0, 4, 5, 7, 3, 2, 1]);
+        // one up or any distance down
+        assert_eq!(
+            vec![(0, 1, 1), (1, 0, 1), (1, 2, 1)],
+            destinations(Climbing::default(), &heightmap)
+        );
+        let climbing = Climbing {
+            max_up: 2,
+            max_down: Some(1),
+            ..Climbing::default()
+        };
+        assert_eq!(
+            vec![(0, 1, 1), (1, 0, 1), (2, 1, 1)],
+            destinations(climbing, &heightmap)
+        );
+        let climbing = Climbing {
+            diagonal: true,
+            cost_per_height: 2,
+            ..Climbing::default()
+        };
+        assert_eq!(
+            vec![
+                (0, 1, 3),
+                (0, 2, 5),
+                (1, 0, 3),
+                (1, 2, 7),
+                (2, 0, 11),
+                (2, 2, 9)
+            ],
+            destinations(climbing, &heightmap)
+        );
+    }
+
+    #[test]
+    fn estimates() {
+        let heightmap = heightmap([9, 6, 0, 4, 5, 7, 3, 2, 1]);
+        let corner = Point::new(0, 0);
+        let far = Point::new(2, 2);
+        assert_eq!(4, Climbing::default().estimate(&heightmap, corner, far));
+        let climbing = Climbing {
+            diagonal: true,
+            cost_per_height: 2,
+            ..Climbing::default()
+        };
+        assert_eq!(2 + 2 * 8, climbing.estimate(&heightmap, corner, far));
+    }
+}
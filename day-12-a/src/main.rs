@@ -1,12 +1,20 @@
+mod climbing;
+mod pathfinding;
+mod render;
+
 use std::{
-    cmp::{max, min, Ordering},
-    collections::HashSet,
+    cmp::{max, min},
+    collections::HashMap,
+    env,
     error::Error,
     fmt::Debug,
     io::{self, BufRead, BufReader},
 };
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+use climbing::Climbing;
+use pathfinding::{Algorithm, Path, Tied};
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Point {
     x: u32,
     y: u32,
@@ -26,17 +34,7 @@ impl Point {
     }
 
     pub fn distance(&self, other: Point) -> u32 {
-        let xd = if self.x > other.x {
-            self.x - other.x
-        } else {
-            other.x - self.x
-        };
-        let yd = if self.y > other.y {
-            self.y - other.y
-        } else {
-            other.y - self.y
-        };
-        return xd + yd;
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
     }
 }
 
@@ -77,6 +75,15 @@ pub struct Grid<T> {
     data: Vec<T>,
 }
 
+impl<T> Default for Grid<T>
+where
+    T: Copy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> Grid<T>
 where
     T: Copy,
@@ -94,10 +101,9 @@ where
 
     pub fn set_size(&mut self, size: Size, default_value: T) {
         if size.width != self.size.width || size.height != self.size.height {
-            let mut new_data = Vec::new();
-            new_data.reserve((size.width * size.height) as usize);
+            let mut new_data = Vec::with_capacity((size.width * size.height) as usize);
             let min_width = min(size.width, self.size.width);
-            let max_width = max(size.width, self.size.height);
+            let max_width = max(size.width, self.size.width);
             let min_height = min(size.height, self.size.height);
             let max_height = max(size.height, self.size.height);
             for y in 0..min_height {
@@ -152,8 +158,7 @@ where
     }
 
     pub fn get_neighbors(&self, p: Point) -> Vec<Point> {
-        let mut results = Vec::new();
-        results.reserve(4);
+        let mut results = Vec::with_capacity(4);
         if p.x >= 1 {
             results.push(Point::new(p.x() - 1, p.y()));
         }
@@ -168,247 +173,274 @@ where
         }
         results
     }
-}
-
-#[derive(Debug, Clone, Copy)]
-enum Direction {
-    Left,
-    Right,
-    Up,
-    Down,
-}
 
-#[derive(Debug, Clone, Copy)]
-enum Solution {
-    Goal,
-    Direction(Direction, u32),
-}
-
-impl Solution {
-    fn score(&self) -> u32 {
-        match self {
-            Solution::Goal => 0,
-            Solution::Direction(_, score) => *score,
+    pub fn get_diagonal_neighbors(&self, p: Point) -> Vec<Point> {
+        let mut results = Vec::with_capacity(4);
+        let right = p.x + 1 < self.size().width();
+        let down = p.y + 1 < self.size().height();
+        if p.x >= 1 && p.y >= 1 {
+            results.push(Point::new(p.x() - 1, p.y() - 1));
+        }
+        if right && p.y >= 1 {
+            results.push(Point::new(p.x() + 1, p.y() - 1));
         }
+        if p.x >= 1 && down {
+            results.push(Point::new(p.x() - 1, p.y() + 1));
+        }
+        if right && down {
+            results.push(Point::new(p.x() + 1, p.y() + 1));
+        }
+        results
     }
 }
 
 #[derive(Debug, Copy, Clone)]
-struct Location {
-    p: Point,
+pub struct Location {
     height: u32,
-    solution: Option<Solution>,
 }
 
 impl Location {
-    pub fn new(p: Point, height: u32) -> Self {
-        Self {
-            p,
-            height,
-            solution: None,
-        }
-    }
-
-    pub fn location(&self) -> Point {
-        self.p
+    pub fn new(height: u32) -> Self {
+        Self { height }
     }
 
     pub fn height(&self) -> u32 {
         self.height
     }
-
-    pub fn can_go_to(&self, other: &Location) -> bool {
-        if self.height + 1 >= other.height {
-            if self.p.x() + 1 == other.p.x() && self.p.y() == other.p.y() {
-                true
-            } else if other.p.x() + 1 == self.p.x() && self.p.y() == other.p.y() {
-                true
-            } else if self.p.x() == other.p.x() && self.p.y() + 1 == other.p.y() {
-                true
-            } else if self.p.x() == other.p.x() && other.p.y() + 1 == self.p.y() {
-                true
-            } else {
-                false
-            }
-        } else {
-            false
-        }
-    }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let mut r = BufReader::new(io::stdin());
-    do_it(&mut r)?;
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+    let (algorithm, climbing, args) = parse_flags(&args)?;
+    match args {
+        [] => {
+            do_it(&mut r, algorithm, climbing)?;
+        }
+        // every point on any of the shortest routes, rather than just one of them
+        ["tied"] => {
+            tied(&mut r, climbing)?;
+        }
+        ["png", path, scale] => {
+            let (heightmap, starts, goal) = parse_heightmap(&mut r)?;
+            let route = find(algorithm, climbing, &heightmap, &starts, goal)?;
+            render::image(&heightmap, &route.points, scale.parse()?)?.save(path)?;
+        }
+        ["tied", "png", path, scale] => {
+            let (heightmap, starts, goal) = parse_heightmap(&mut r)?;
+            let tied = all_shortest(climbing, &heightmap, &starts, goal)?;
+            render::image(&heightmap, &tied.points, scale.parse()?)?.save(path)?;
+        }
+        _ => Err(format!("unrecognized arguments: {:?}", args))?,
+    };
     Ok(())
 }
 
-fn do_it(r: &mut impl std::io::Read) -> Result<usize, Box<dyn Error>> {
-    let mut heightmap = Grid::new();
-    let mut start = None;
-    let mut goal = None;
-    {
-        let mut y = 0;
-        for line in BufReader::new(r).lines() {
-            let line = line?;
-            let mut x = 0;
-            for c in line.chars() {
-                let p = Point::new(x, y);
-                heightmap.grow(Size::new(x + 1, y + 1), Location::new(Point::new(0, 0), 0));
-                match c {
-                    'S' => {
-                        heightmap.set(p, Location::new(p, 0))?;
-                        start = Some(Point::new(x, y));
-                    }
-                    'E' => {
-                        let mut location = Location::new(p, 25);
-                        location.solution = Some(Solution::Goal);
-                        heightmap.set(p, location)?;
-                        goal = Some(Point::new(x, y));
-                    }
-                    'a'..='z' => {
-                        heightmap.set(p, Location::new(p, c as u32 - 'a' as u32))?;
-                    }
-                    _ => Err(format!("unrecognized char: {}", c))?,
-                };
-                x += 1;
+// "--search bfs|dijkstra|astar", "--up N", "--down N", "--diagonal" and "--climb-cost N" before
+// the command
+fn parse_flags<'a, 'b>(
+    args: &'b [&'a str],
+) -> Result<(Algorithm, Climbing, &'b [&'a str]), Box<dyn Error>> {
+    let mut algorithm = Algorithm::BreadthFirst;
+    let mut climbing = Climbing::default();
+    let mut args = args;
+    loop {
+        args = match args {
+            ["--search", value, rest @ ..] => {
+                algorithm = value.parse()?;
+                rest
             }
-            y += 1;
-        }
-    }
-    let start = start.ok_or("no start")?;
-    let goal = goal.ok_or("no goal")?;
-
-    println!("heightmap size = {:?}", heightmap.size());
-    for y in 0..heightmap.size().height() {
-        for x in 0..heightmap.size().width() {
-            let value = heightmap.get(Point::new(x, y))?;
-            let c = (value.height as u8 + 'a' as u8) as char;
-            print!("{}", c);
-        }
-        println!("");
-    }
-    println!("start = {:?}", start);
-    println!("goal = {:?}", goal);
-    println!("");
-
-    let find_locations_that_can_reach =
-        |heightmap: &Grid<Location>, goal: Point| -> Vec<(Direction, Point)> {
-            [
-                if goal.x() >= 1 {
-                    Some((Direction::Right, Point::new(goal.x() - 1, goal.y())))
-                } else {
-                    None
-                },
-                if goal.x() + 1 < heightmap.size().width() {
-                    Some((Direction::Left, Point::new(goal.x() + 1, goal.y())))
-                } else {
-                    None
-                },
-                if goal.y() >= 1 {
-                    Some((Direction::Down, Point::new(goal.x(), goal.y() - 1)))
-                } else {
-                    None
-                },
-                if goal.y() + 1 < heightmap.size().height() {
-                    Some((Direction::Up, Point::new(goal.x(), goal.y() + 1)))
-                } else {
-                    None
-                },
-            ]
-            .iter()
-            .filter(|x| x.is_some())
-            .map(|x| x.unwrap())
-            .filter(|(_, location)| {
-                let location = heightmap.get(*location);
-                let goal_location = heightmap.get(goal);
-                if location.is_err() || goal_location.is_err() {
-                    false
-                } else {
-                    location.unwrap().can_go_to(&goal_location.unwrap())
-                }
-            })
-            .collect::<Vec<_>>()
+            ["--up", value, rest @ ..] => {
+                climbing.max_up = value.parse()?;
+                rest
+            }
+            ["--down", value, rest @ ..] => {
+                climbing.max_down = Some(value.parse()?);
+                rest
+            }
+            ["--diagonal", rest @ ..] => {
+                climbing.diagonal = true;
+                rest
+            }
+            ["--climb-cost", value, rest @ ..] => {
+                climbing.cost_per_height = value.parse()?;
+                rest
+            }
+            _ => break,
         };
+    }
+    Ok((algorithm, climbing, args))
+}
 
-    // the places we can search are those locations that can go to the goal
-    let mut locations_to_check = vec![goal];
-
-    // while there are locations to search, go fill in one
-    while !locations_to_check.is_empty() {
-        let location = locations_to_check.pop().unwrap();
-        let location = heightmap.get(location)?;
-        let location_score = location.solution.unwrap().score();
-        for (direction, other) in find_locations_that_can_reach(&heightmap, location.location()) {
-            let mut other = heightmap.get(other)?;
-            // if we accept this direction
-            let proposed_other_score = location_score + 1;
-            if match other.solution {
-                Some(other_solution) => proposed_other_score < other_solution.score(),
-                None => true,
-            } {
-                other.solution = Some(Solution::Direction(direction, proposed_other_score));
-                heightmap.set(other.location(), other)?;
-                locations_to_check.push(other.location());
+fn find(
+    algorithm: Algorithm,
+    climbing: Climbing,
+    heightmap: &Grid<Location>,
+    starts: &[Point],
+    goal: Point,
+) -> Result<Path, Box<dyn Error>> {
+    let moves = |p| climbing.moves(heightmap, p);
+    let path = match algorithm {
+        Algorithm::BreadthFirst => {
+            // which only counts steps
+            if climbing.cost_per_height != 0 {
+                Err("bfs can't take climbing costs into account, use dijkstra or astar")?
             }
+            pathfinding::breadth_first(heightmap, starts, &[goal], |p| {
+                moves(p).into_iter().map(|(next, _)| next).collect()
+            })
         }
+        Algorithm::Dijkstra => pathfinding::dijkstra(heightmap, starts, &[goal], moves),
+        Algorithm::AStar => pathfinding::a_star(heightmap, starts, &[goal], moves, |p| {
+            climbing.estimate(heightmap, p, goal)
+        }),
+    };
+    Ok(path.ok_or("no way to reach the goal")?)
+}
+
+fn all_shortest(
+    climbing: Climbing,
+    heightmap: &Grid<Location>,
+    starts: &[Point],
+    goal: Point,
+) -> Result<Tied, Box<dyn Error>> {
+    let moves = |p| climbing.moves(heightmap, p);
+    Ok(pathfinding::all_shortest(starts, &[goal], moves).ok_or("no way to reach the goal")?)
+}
+
+fn do_it(
+    r: &mut impl std::io::Read,
+    algorithm: Algorithm,
+    climbing: Climbing,
+) -> Result<usize, Box<dyn Error>> {
+    let (heightmap, starts, goal) = parse_heightmap(r)?;
+    let path = find(algorithm, climbing, &heightmap, &starts, goal)?;
+    println!("path = {:?}", path.points);
+    print!(
+        "{}",
+        render::ascii(&heightmap, &render::arrows(&path.points))
+    );
+    println!("real start = {:?}", path.points[0]);
+    println!("cost = {}", path.cost);
+    println!("moves to reach goal = {}", path.steps());
+    Ok(path.steps())
+}
+
+fn tied(r: &mut impl std::io::Read, climbing: Climbing) -> Result<Tied, Box<dyn Error>> {
+    let (heightmap, starts, goal) = parse_heightmap(r)?;
+    let tied = all_shortest(climbing, &heightmap, &starts, goal)?;
+    let overlay = tied
+        .points
+        .iter()
+        .map(|p| (*p, '#'))
+        .collect::<HashMap<_, _>>();
+    print!("{}", render::ascii(&heightmap, &overlay));
+    println!("cost = {}", tied.cost);
+    if tied.count == u128::MAX {
+        println!("tied routes = too many to count");
+    } else {
+        println!("tied routes = {}", tied.count);
     }
+    Ok(tied)
+}
 
-    for y in 0..heightmap.size().height() {
-        for x in 0..heightmap.size().width() {
-            let location = heightmap.get(Point::new(x, y))?;
-            let c = match location.solution {
-                Some(Solution::Direction(direction, _)) => match (direction) {
-                    Direction::Left => '<',
-                    Direction::Right => '>',
-                    Direction::Up => '^',
-                    Direction::Down => 'v',
-                },
-                Some(Solution::Goal) => 'E',
-                None => '.',
+// the heightmap, where to start from, and the goal
+type Puzzle = (Grid<Location>, Vec<Point>, Point);
+
+fn parse_heightmap(r: &mut impl std::io::Read) -> Result<Puzzle, Box<dyn Error>> {
+    let mut heightmap = Grid::new();
+    let mut start = None;
+    let mut goal = None;
+    for (y, line) in BufReader::new(r).lines().enumerate() {
+        let line = line?;
+        for (x, c) in line.chars().enumerate() {
+            let p = Point::new(x as u32, y as u32);
+            heightmap.grow(Size::new(p.x() + 1, p.y() + 1), Location::new(0));
+            let height = match c {
+                'S' => {
+                    start = Some(p);
+                    0
+                }
+                'E' => {
+                    goal = Some(p);
+                    25
+                }
+                'a'..='z' => c as u32 - 'a' as u32,
+                _ => Err(format!("unrecognized char: {}", c))?,
             };
-            print!("{}", c);
+            heightmap.set(p, Location::new(height))?;
         }
-        println!("");
     }
+    let start = start.ok_or("no start")?;
+    let goal = goal.ok_or("no goal")?;
 
-    let result = {
-        let mut current = start;
-        let mut count = 0;
-        while current != goal {
-            current = match heightmap.get(current)?.solution {
-                Some(Solution::Direction(direction, _)) => match direction {
-                    Direction::Left => Point::new(current.x() - 1, current.y()),
-                    Direction::Right => Point::new(current.x() + 1, current.y()),
-                    Direction::Up => Point::new(current.x(), current.y() - 1),
-                    Direction::Down => Point::new(current.x(), current.y() + 1),
-                },
-                _ => Err(format!("bad solution at {:?}", current))?,
-            };
-            count += 1;
-        }
-        count
-    };
-    println!("moves to reach goal = {}", result);
-    Ok(result)
+    println!("heightmap size = {:?}", heightmap.size());
+    println!("start = {:?}", start);
+    println!("goal = {:?}", goal);
+    println!();
+    Ok((heightmap, vec![start], goal))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn sample() -> Result<(), Box<dyn Error>> {
-        assert_eq!(
-            31,
-            do_it(
-                &mut r"Sabqponm
+    const SAMPLE: &str = r"Sabqponm
 abcryxxl
 accszExk
 acctuvwj
-abdefghi"
-                    .as_bytes(),
-            )?
+abdefghi";
+
+    #[test]
+    fn sample() -> Result<(), Box<dyn Error>> {
+        for algorithm in [
+            Algorithm::BreadthFirst,
+            Algorithm::Dijkstra,
+            Algorithm::AStar,
+        ] {
+            assert_eq!(
+                31,
+                do_it(&mut SAMPLE.as_bytes(), algorithm, Climbing::default())?
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn climbing() -> Result<(), Box<dyn Error>> {
+        let tied = tied(&mut SAMPLE.as_bytes(), Climbing::default())?;
+        assert_eq!((31, 7), (tied.cost, tied.count));
+
+        let diagonal = Climbing {
+            diagonal: true,
+            ..Climbing::default()
+        };
+        assert_eq!(
+            27,
+            do_it(&mut SAMPLE.as_bytes(), Algorithm::AStar, diagonal)?
         );
+        let steep = Climbing {
+            max_up: 2,
+            max_down: Some(1),
+            ..Climbing::default()
+        };
+        assert_eq!(
+            27,
+            do_it(&mut SAMPLE.as_bytes(), Algorithm::Dijkstra, steep)?
+        );
+
+        // climbing as little as possible, which here is only ever going up
+        let costly = Climbing {
+            cost_per_height: 2,
+            ..Climbing::default()
+        };
+        let (heightmap, starts, goal) = parse_heightmap(&mut SAMPLE.as_bytes())?;
+        for algorithm in [Algorithm::Dijkstra, Algorithm::AStar] {
+            let path = find(algorithm, costly, &heightmap, &starts, goal)?;
+            assert_eq!(81, path.cost);
+        }
+        assert!(find(Algorithm::BreadthFirst, costly, &heightmap, &starts, goal).is_err());
         Ok(())
     }
 }
@@ -0,0 +1,365 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+    str::FromStr,
+};
+
+use crate::{Grid, Point};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    BreadthFirst,
+    Dijkstra,
+    AStar,
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bfs" => Ok(Algorithm::BreadthFirst),
+            "dijkstra" => Ok(Algorithm::Dijkstra),
+            "astar" => Ok(Algorithm::AStar),
+            _ => Err(format!("unrecognized search: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path {
+    // from whichever source was closest to whichever target it reached first, inclusive
+    pub points: Vec<Point>,
+    pub cost: u32,
+}
+
+impl Path {
+    pub fn steps(&self) -> usize {
+        self.points.len() - 1
+    }
+}
+
+// every tied cheapest way from any source to any nearest target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tied {
+    pub cost: u32,
+    // how many different paths there are, which can be a lot more than there are points, so it
+    // stops at u128::MAX
+    pub count: u128,
+    // every point on at least one of them, in order
+    pub points: Vec<Point>,
+}
+
+// the fewest moves from any source to any target, where moves says where you can go from a point
+pub fn breadth_first<T: Copy>(
+    grid: &Grid<T>,
+    sources: &[Point],
+    targets: &[Point],
+    moves: impl Fn(Point) -> Vec<Point>,
+) -> Option<Path> {
+    let mut came_from = Searched::new(grid);
+    let mut queue = VecDeque::new();
+    for source in sources.iter() {
+        if came_from.visit(*source, None, 0) {
+            queue.push_back(*source);
+        }
+    }
+    while let Some(p) = queue.pop_front() {
+        let cost = came_from.cost(p);
+        if targets.contains(&p) {
+            return Some(came_from.path(p));
+        }
+        for next in moves(p) {
+            if came_from.visit(next, Some(p), cost + 1) {
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+// the cheapest way from any source to any target, where moves also says what each move costs
+pub fn dijkstra<T: Copy>(
+    grid: &Grid<T>,
+    sources: &[Point],
+    targets: &[Point],
+    moves: impl Fn(Point) -> Vec<(Point, u32)>,
+) -> Option<Path> {
+    a_star(grid, sources, targets, moves, |_| 0)
+}
+
+// dijkstra guided by a heuristic, which must never be more than the real cost to the nearest target
+pub fn a_star<T: Copy>(
+    grid: &Grid<T>,
+    sources: &[Point],
+    targets: &[Point],
+    moves: impl Fn(Point) -> Vec<(Point, u32)>,
+    heuristic: impl Fn(Point) -> u32,
+) -> Option<Path> {
+    let mut came_from = Searched::new(grid);
+    let mut queue = BinaryHeap::new();
+    for source in sources.iter() {
+        if came_from.visit(*source, None, 0) {
+            queue.push(Reverse((heuristic(*source), 0, *source)));
+        }
+    }
+    while let Some(Reverse((_, so_far, p))) = queue.pop() {
+        // already found a cheaper way here since this was queued
+        if so_far > came_from.cost(p) {
+            continue;
+        }
+        if targets.contains(&p) {
+            return Some(came_from.path(p));
+        }
+        for (next, step) in moves(p) {
+            let total = so_far + step;
+            if came_from.visit(next, Some(p), total) {
+                queue.push(Reverse((total + heuristic(next), total, next)));
+            }
+        }
+    }
+    None
+}
+
+// like dijkstra, but keeps every way of getting to each point at its cheapest cost, so moves must
+// all cost something for the counts to come out right
+pub fn all_shortest(
+    sources: &[Point],
+    targets: &[Point],
+    moves: impl Fn(Point) -> Vec<(Point, u32)>,
+) -> Option<Tied> {
+    // the cheapest cost to each point, everywhere it can be reached from at that cost, and how many
+    // paths that makes
+    let mut best = HashMap::<Point, (u32, Vec<Point>, u128)>::new();
+    let mut queue = BinaryHeap::new();
+    for source in sources.iter() {
+        if best.insert(*source, (0, Vec::new(), 1)).is_none() {
+            queue.push(Reverse((0, *source)));
+        }
+    }
+    let mut found = None;
+    let mut reached = Vec::new();
+    while let Some(Reverse((so_far, p))) = queue.pop() {
+        if so_far > best[&p].0 || found.is_some_and(|cost| so_far > cost) {
+            continue;
+        }
+        if targets.contains(&p) {
+            found = Some(so_far);
+            reached.push(p);
+            continue;
+        }
+        // everything that can get here has already been taken off the queue, so this is final
+        let count = best[&p].2;
+        for (next, step) in moves(p) {
+            let total = so_far + step;
+            match best.get_mut(&next) {
+                Some((cost, _, _)) if *cost < total => {}
+                Some((cost, from, ways)) if *cost == total => {
+                    from.push(p);
+                    *ways = ways.saturating_add(count);
+                }
+                _ => {
+                    best.insert(next, (total, vec![p], count));
+                    queue.push(Reverse((total, next)));
+                }
+            }
+        }
+    }
+
+    // work back from the targets through everywhere each point could have been reached from
+    let cost = found?;
+    let count = reached
+        .iter()
+        .fold(0u128, |total, p| total.saturating_add(best[p].2));
+    let mut points = HashSet::new();
+    let mut pending = reached;
+    while let Some(p) = pending.pop() {
+        if points.insert(p) {
+            pending.extend(best[&p].1.iter().copied());
+        }
+    }
+    let mut points = points.into_iter().collect::<Vec<_>>();
+    points.sort_by_key(|p| (p.y(), p.x()));
+    Some(Tied {
+        cost,
+        count,
+        points,
+    })
+}
+
+// the cheapest known cost to each point and where it came from
+struct Searched {
+    best: Grid<Option<(u32, Option<Point>)>>,
+}
+
+impl Searched {
+    fn new<T: Copy>(grid: &Grid<T>) -> Self {
+        let mut best = Grid::new();
+        best.set_size(grid.size(), None);
+        Self { best }
+    }
+
+    // records a way to get to p, returning whether it's better than anything seen so far
+    fn visit(&mut self, p: Point, from: Option<Point>, cost: u32) -> bool {
+        match self.best.get(p) {
+            Ok(Some((best, _))) if best <= cost => false,
+            Ok(_) => {
+                self.best.set(p, Some((cost, from))).unwrap();
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn cost(&self, p: Point) -> u32 {
+        self.best.get(p).unwrap().unwrap().0
+    }
+
+    fn path(&self, target: Point) -> Path {
+        let mut points = vec![target];
+        while let Some((_, Some(from))) = self.best.get(*points.last().unwrap()).unwrap() {
+            points.push(from);
+        }
+        points.reverse();
+        Path {
+            points,
+            cost: self.cost(target),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Size;
+
+    // # is a wall, digits are how much it costs to step onto a cell
+    fn grid(s: &str) -> Grid<char> {
+        let mut result = Grid::new();
+        for (y, line) in s.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                result.grow(Size::new(x as u32 + 1, y as u32 + 1), '#');
+                result.set(Point::new(x as u32, y as u32), c).unwrap();
+            }
+        }
+        result
+    }
+
+    fn steps(grid: &Grid<char>) -> impl Fn(Point) -> Vec<Point> + '_ {
+        |p| {
+            grid.get_neighbors(p)
+                .into_iter()
+                .filter(|next| grid.get(*next).unwrap() != '#')
+                .collect()
+        }
+    }
+
+    fn cost(grid: &Grid<char>) -> impl Fn(Point) -> Vec<(Point, u32)> + '_ {
+        |p| {
+            grid.get_neighbors(p)
+                .into_iter()
+                .filter_map(|next| Some((next, grid.get(next).unwrap().to_digit(10)?)))
+                .collect()
+        }
+    }
+
+    fn manhattan(targets: &[Point]) -> impl Fn(Point) -> u32 + '_ {
+        |p| targets.iter().map(|t| p.distance(*t)).min().unwrap()
+    }
+
+    #[test]
+    fn fewest_steps() {
+        let g = grid("1111\n1##1\n1#11\n1191");
+        let path = breadth_first(&g, &[Point::new(0, 0)], &[Point::new(2, 2)], steps(&g)).unwrap();
+        assert_eq!(6, path.steps());
+        assert_eq!(Point::new(0, 0), path.points[0]);
+        assert_eq!(Point::new(2, 2), *path.points.last().unwrap());
+        for pair in path.points.windows(2) {
+            assert_eq!(1, pair[0].distance(pair[1]));
+        }
+    }
+
+    #[test]
+    fn cheapest() {
+        // going round the 9 is cheaper than going through it
+        let g = grid("1111\n1##1\n1#11\n1191");
+        let targets = [Point::new(2, 2)];
+        let expected = Path {
+            points: [(0, 0), (1, 0), (2, 0), (3, 0), (3, 1), (3, 2), (2, 2)]
+                .iter()
+                .map(|(x, y)| Point::new(*x, *y))
+                .collect(),
+            cost: 6,
+        };
+        assert_eq!(
+            Some(&expected),
+            dijkstra(&g, &[Point::new(0, 0)], &targets, cost(&g)).as_ref()
+        );
+        assert_eq!(
+            Some(&expected),
+            a_star(
+                &g,
+                &[Point::new(0, 0)],
+                &targets,
+                cost(&g),
+                manhattan(&targets)
+            )
+            .as_ref()
+        );
+    }
+
+    #[test]
+    fn many_sources_and_targets() {
+        let g = grid("1111\n1##1\n1#11\n1191");
+        let sources = [Point::new(1, 3), Point::new(3, 0)];
+        let targets = [Point::new(0, 0), Point::new(2, 3)];
+        let path = dijkstra(&g, &sources, &targets, cost(&g)).unwrap();
+        // (3, 0) to (0, 0) costs 3, (1, 3) to either costs at least 4
+        assert_eq!(3, path.cost);
+        assert_eq!(Point::new(3, 0), path.points[0]);
+        assert_eq!(
+            Some(3),
+            a_star(&g, &sources, &targets, cost(&g), manhattan(&targets)).map(|p| p.cost)
+        );
+        assert_eq!(
+            None,
+            breadth_first(&g, &sources, &[Point::new(1, 1)], steps(&g))
+        );
+        assert_eq!(None, dijkstra(&g, &sources, &[Point::new(1, 1)], cost(&g)));
+    }
+
+    #[test]
+    fn ties() {
+        let g = grid("1111\n1##1\n1#11\n1191");
+        let corner = [Point::new(0, 0)];
+        // going through the 9 is never as cheap as going round the top
+        let tied = all_shortest(&corner, &[Point::new(3, 3)], cost(&g)).unwrap();
+        assert_eq!(6, tied.cost);
+        assert_eq!(1, tied.count);
+        assert_eq!(7, tied.points.len());
+
+        // every way across an open grid is as short as every other
+        let g = grid("111\n111\n111");
+        let tied = all_shortest(&corner, &[Point::new(2, 2)], cost(&g)).unwrap();
+        assert_eq!(4, tied.cost);
+        assert_eq!(6, tied.count);
+        assert_eq!(9, tied.points.len());
+
+        // only the nearest targets count
+        let targets = [Point::new(1, 1), Point::new(2, 2)];
+        let tied = all_shortest(&corner, &targets, cost(&g)).unwrap();
+        assert_eq!((2, 2), (tied.cost, tied.count));
+        assert_eq!(
+            vec![
+                Point::new(0, 0),
+                Point::new(1, 0),
+                Point::new(0, 1),
+                Point::new(1, 1)
+            ],
+            tied.points
+        );
+        // nothing leads outside the grid
+        assert_eq!(None, all_shortest(&corner, &[Point::new(3, 3)], cost(&g)));
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use crate::{Grid, Location, Point};
+
+// which way each step of the path goes, and an E where it ends
+pub fn arrows(path: &[Point]) -> HashMap<Point, char> {
+    let mut result = HashMap::new();
+    for pair in path.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        let dx = to.x() as i64 - from.x() as i64;
+        let dy = to.y() as i64 - from.y() as i64;
+        let c = match (dx, dy) {
+            (1, 0) => '>',
+            (-1, 0) => '<',
+            (0, 1) => 'v',
+            (0, -1) => '^',
+            (1, 1) | (-1, -1) => '\\',
+            _ => '/',
+        };
+        result.insert(from, c);
+    }
+    if let Some(end) = path.last() {
+        result.insert(*end, 'E');
+    }
+    result
+}
+
+// the heightmap as letters, with anything in the overlay drawn over the top
+pub fn ascii(heightmap: &Grid<Location>, overlay: &HashMap<Point, char>) -> String {
+    let mut result = String::new();
+    for y in 0..heightmap.size().height() {
+        for x in 0..heightmap.size().width() {
+            let p = Point::new(x, y);
+            let c = overlay.get(&p).copied().unwrap_or_else(|| {
+                let height = heightmap.get(p).unwrap().height();
+                (height as u8 + b'a') as char
+            });
+            result.push(c);
+        }
+        result.push('\n');
+    }
+    result
+}
+
+// the heightmap in shades of grey from black at a to white at z, with the route in red
+pub fn image(
+    heightmap: &Grid<Location>,
+    route: &[Point],
+    scale: u32,
+) -> Result<image::RgbImage, String> {
+    if scale == 0 {
+        Err("scale must be positive")?
+    }
+    let size = heightmap.size();
+    let mut result =
+        image::RgbImage::from_fn(size.width() * scale, size.height() * scale, |x, y| {
+            let height = heightmap
+                .get(Point::new(x / scale, y / scale))
+                .unwrap()
+                .height();
+            let shade = (height.min(25) * 10) as u8;
+            image::Rgb([shade, shade, shade])
+        });
+    for p in route.iter() {
+        for dy in 0..scale {
+            for dx in 0..scale {
+                result.put_pixel(
+                    p.x() * scale + dx,
+                    p.y() * scale + dy,
+                    image::Rgb([255, 0, 0]),
+                );
+            }
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Size;
+
+    fn heightmap(rows: &str) -> Grid<Location> {
+        let mut result = Grid::new();
+        for (y, line) in rows.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let p = Point::new(x as u32, y as u32);
+                result.grow(Size::new(p.x() + 1, p.y() + 1), Location::new(0));
+                result.set(p, Location::new(c as u32 - 'a' as u32)).unwrap();
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn overlays() {
+        let heightmap = heightmap("abc\nfed\nghi");
+        let path = [(0, 0), (1, 0), (2, 0), (2, 1), (1, 2), (0, 2), (0, 1)]
+            .iter()
+            .map(|(x, y)| Point::new(*x, *y))
+            .collect::<Vec<_>>();
+        assert_eq!(">>v\nEe/\n^<i\n", ascii(&heightmap, &arrows(&path)));
+        let down_right = [Point::new(0, 0), Point::new(1, 1)];
+        assert_eq!("\\bc\nfEd\nghi\n", ascii(&heightmap, &arrows(&down_right)));
+        assert_eq!("abc\nfed\nghi\n", ascii(&heightmap, &HashMap::new()));
+    }
+
+    #[test]
+    fn image() -> Result<(), String> {
+        let heightmap = heightmap("az\nza");
+        let image = super::image(&heightmap, &[Point::new(1, 1)], 2)?;
+        assert_eq!((4, 4), image.dimensions());
+        assert_eq!(&image::Rgb([0, 0, 0]), image.get_pixel(1, 1));
+        assert_eq!(&image::Rgb([250, 250, 250]), image.get_pixel(2, 1));
+        assert_eq!(&image::Rgb([255, 0, 0]), image.get_pixel(3, 3));
+        assert!(super::image(&heightmap, &[], 0).is_err());
+        Ok(())
+    }
+}
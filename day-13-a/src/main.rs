@@ -1,106 +1,90 @@
-use serde_json::json;
+mod packet;
+
 use std::{
     cmp::Ordering,
+    env,
     error::Error,
     io::{self, BufRead, BufReader},
 };
 
+use packet::{Explanation, Packet};
+
 fn main() -> Result<(), Box<dyn Error>> {
     let mut r = BufReader::new(io::stdin());
-    do_it(&mut r)?;
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+    match args.as_slice() {
+        [] => {
+            do_it(&mut r)?;
+        }
+        // why a pair, numbered from 1, is in the order it is
+        ["explain", index] => {
+            explain(&mut r, index.parse()?)?;
+        }
+        _ => Err(format!("unrecognized arguments: {:?}", args))?,
+    };
     Ok(())
 }
 
 fn do_it(r: &mut impl std::io::Read) -> Result<usize, Box<dyn Error>> {
-    let lines = BufReader::new(r)
-        .lines()
-        // get rid of any broken lines
-        .collect::<Result<Vec<_>, _>>()?
-        .iter()
-        // get rid of empty lines
-        .filter(|line| !line.trim().is_empty())
-        // parse as json
-        .map(|line| serde_json::from_str::<serde_json::Value>(line))
-        // get rid of any parse errors
-        .collect::<Result<Vec<_>, _>>()?;
-    if lines.len() % 2 != 0 {
+    let packets = parse_packets(r)?;
+    if packets.len() % 2 != 0 {
         Err(format!(
-            "expected an even number of lines, got {}",
-            lines.len()
+            "expected an even number of packets, got {}",
+            packets.len()
         ))?;
     }
 
-    let mut index = 1;
     let mut sum = 0;
-    for i in (0..lines.len()).step_by(2) {
-        let left = &lines[i];
-        let right = &lines[i + 1];
-        let comparison = compare_pair(left, right)?;
+    for (i, pair) in packets.chunks(2).enumerate() {
+        let index = i + 1;
+        let (left, right) = (&pair[0], &pair[1]);
+        let comparison = left.cmp(right);
         println!(
-            "parsed input pair\nindex = {}\nleft = {:?}\nright = {:?}\ncomparison = {:?}\n",
+            "parsed input pair\nindex = {}\nleft = {}\nright = {}\ncomparison = {:?}\n",
             index, left, right, comparison
         );
         if comparison == Ordering::Less {
             sum += index;
         }
-        index += 1;
     }
     println!("sum of indices in the right order = {}", sum);
     Ok(sum)
 }
 
-fn compare_pair(
-    left: &serde_json::Value,
-    right: &serde_json::Value,
-) -> Result<std::cmp::Ordering, Box<dyn Error>> {
-    if left.is_number() && right.is_number() {
-        let left = assert_number(left)?;
-        let right = assert_number(right)?;
-        Ok(left.cmp(&right))
-    } else if left.is_array() && right.is_array() {
-        let left = assert_array(left)?;
-        let right = assert_array(right)?;
-        let mut i = 0;
-        loop {
-            if i >= left.len() && i >= right.len() {
-                return Ok(Ordering::Equal);
-            }
-            if i >= left.len() {
-                return Ok(Ordering::Less);
-            }
-            if i >= right.len() {
-                return Ok(Ordering::Greater);
-            }
-            let result = compare_pair(&left[i], &right[i])?;
-            if result == Ordering::Less || result == Ordering::Greater {
-                return Ok(result);
-            }
-            i += 1;
-        }
-    } else if left.is_number() && right.is_array() {
-        let left = &json!([assert_number(left)?]);
-        compare_pair(left, right)
-    } else if left.is_array() && right.is_number() {
-        let right = &json!([assert_number(right)?]);
-        compare_pair(left, right)
-    } else {
-        Err(format!(
-            "types aren't numbers or arrays, left = {:?}, right = {:?}",
-            left, right
-        ))?
+fn explain(r: &mut impl std::io::Read, index: usize) -> Result<Explanation, Box<dyn Error>> {
+    let packets = parse_packets(r)?;
+    let pairs = packets.len() / 2;
+    if index == 0 || index > pairs {
+        Err(format!("no pair {}, there are {}", index, pairs))?
     }
+    let (left, right) = (&packets[index * 2 - 2], &packets[index * 2 - 1]);
+    let explanation = packet::explain(left, right);
+    let order = match explanation.ordering {
+        Ordering::Less => "the right order",
+        Ordering::Equal => "neither order",
+        Ordering::Greater => "the wrong order",
+    };
+    println!("pair {} is in {}, {}", index, order, explanation);
+    println!("{}", left.highlight(&explanation.path));
+    println!("{}", right.highlight(&explanation.path));
+    Ok(explanation)
 }
 
-fn assert_number(value: &serde_json::Value) -> Result<i64, Box<dyn Error>> {
-    Ok(value
-        .as_i64()
-        .ok_or(format!("expected number: {}", value))?)
-}
-
-fn assert_array(value: &serde_json::Value) -> Result<&Vec<serde_json::Value>, Box<dyn Error>> {
-    Ok(value
-        .as_array()
-        .ok_or(format!("expected array: {}", value))?)
+// one packet per line, with blank lines in between pairs
+fn parse_packets(r: &mut impl std::io::Read) -> Result<Vec<Packet>, Box<dyn Error>> {
+    let mut results = Vec::new();
+    for (i, line) in BufReader::new(r).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let packet = line
+            .parse::<Packet>()
+            .map_err(|e| format!("line {}: {}", i + 1, e))?;
+        results.push(packet);
+    }
+    Ok(results)
 }
 
 #[cfg(test)]
@@ -109,12 +93,7 @@ mod tests {
 
     use super::*;
 
-    #[test]
-    fn sample() -> Result<(), Box<dyn Error>> {
-        assert_eq!(
-            13,
-            do_it(
-                &mut r"[1,1,3,1,1]
+    const SAMPLE: &str = r"[1,1,3,1,1]
 [1,1,5,1,1]
 
 [[1],[2,3,4]]
@@ -136,10 +115,31 @@ mod tests {
 [[]]
 
 [1,[2,[3,[4,[5,6,7]]]],8,9]
-[1,[2,[3,[4,[5,6,0]]]],8,9]"
-                    .as_bytes(),
-            )?
+[1,[2,[3,[4,[5,6,0]]]],8,9]";
+
+    #[test]
+    fn sample() -> Result<(), Box<dyn Error>> {
+        assert_eq!(13, do_it(&mut SAMPLE.as_bytes())?);
+        Ok(())
+    }
+
+    #[test]
+    fn bad_packet() {
+        assert_eq!(
+            "line 2: column 4: expected a number or [: ']'",
+            do_it(&mut "[1]\n[1,]".as_bytes())
+                .err()
+                .unwrap()
+                .to_string()
         );
+    }
+
+    #[test]
+    fn explained() -> Result<(), Box<dyn Error>> {
+        let explanation = explain(&mut SAMPLE.as_bytes(), 8)?;
+        assert_eq!("at [1][1][1][1][2]: 7 > 0", explanation.to_string());
+        assert!(explain(&mut SAMPLE.as_bytes(), 9).is_err());
+        assert!(explain(&mut SAMPLE.as_bytes(), 0).is_err());
         Ok(())
     }
 }
@@ -0,0 +1,391 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+#[derive(Debug, Clone)]
+pub enum Packet {
+    Int(u64),
+    List(Vec<Packet>),
+}
+
+impl Packet {
+    // an integer compares as if it were a list of just itself, without having to make that list
+    fn as_slice(&self) -> &[Packet] {
+        match self {
+            Packet::Int(_) => std::slice::from_ref(self),
+            Packet::List(items) => items,
+        }
+    }
+
+    // how long it is when displayed, without displaying it
+    fn display_len(&self) -> usize {
+        match self {
+            Packet::Int(n) => n.checked_ilog10().map_or(1, |digits| digits as usize + 1),
+            Packet::List(items) => {
+                let commas = items.len().saturating_sub(1);
+                2 + commas + items.iter().map(|item| item.display_len()).sum::<usize>()
+            }
+        }
+    }
+
+    // where whatever's at the path starts and ends when displayed, or the ] of the list it's past
+    // the end of. only the items before each step on the way down get measured, so nothing is
+    // measured twice
+    fn span(&self, path: &[usize]) -> (usize, usize) {
+        match (self, path.split_first()) {
+            (Packet::List(items), Some((i, rest))) => match items.get(*i) {
+                Some(item) => {
+                    let before = items[..*i]
+                        .iter()
+                        .map(|item| item.display_len() + 1)
+                        .sum::<usize>();
+                    let (start, end) = item.span(rest);
+                    (1 + before + start, 1 + before + end)
+                }
+                None => {
+                    let len = self.display_len();
+                    (len - 1, len)
+                }
+            },
+            (Packet::Int(_), Some((0, rest))) => self.span(rest),
+            _ => (0, self.display_len()),
+        }
+    }
+
+    // the packet, and a line underneath marking whatever's at the path
+    pub fn highlight(&self, path: &[usize]) -> String {
+        let (start, end) = self.span(path);
+        format!("{}\n{}{}", self, " ".repeat(start), "^".repeat(end - start))
+    }
+}
+
+// what decided how two packets compare
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    Ints(u64, u64),
+    LeftRanOut,
+    RightRanOut,
+    // nothing did
+    Same,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub ordering: Ordering,
+    // the indices into both packets down to where it was decided
+    pub path: Vec<usize>,
+    pub reason: Reason,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let at = if self.path.is_empty() {
+            "the top".to_string()
+        } else {
+            self.path.iter().map(|i| format!("[{}]", i)).collect()
+        };
+        match self.reason {
+            Reason::Ints(left, right) => {
+                let symbol = match self.ordering {
+                    Ordering::Less => "<",
+                    Ordering::Equal => "=",
+                    Ordering::Greater => ">",
+                };
+                write!(f, "at {}: {} {} {}", at, left, symbol, right)
+            }
+            Reason::LeftRanOut => write!(f, "left ran out of items at {}", at),
+            Reason::RightRanOut => write!(f, "right ran out of items at {}", at),
+            Reason::Same => write!(f, "neither comes first"),
+        }
+    }
+}
+
+// the same as comparing them, but keeping track of where it was decided
+pub fn explain(left: &Packet, right: &Packet) -> Explanation {
+    let mut path = Vec::new();
+    let (ordering, reason) = explain_at(left, right, &mut path);
+    if ordering == Ordering::Equal {
+        path.clear();
+    }
+    Explanation {
+        ordering,
+        path,
+        reason,
+    }
+}
+
+fn explain_at(left: &Packet, right: &Packet, path: &mut Vec<usize>) -> (Ordering, Reason) {
+    if let (Packet::Int(l), Packet::Int(r)) = (left, right) {
+        return (l.cmp(r), Reason::Ints(*l, *r));
+    }
+    let (left, right) = (left.as_slice(), right.as_slice());
+    for i in 0..=left.len().max(right.len()) {
+        path.push(i);
+        match (left.get(i), right.get(i)) {
+            (Some(l), Some(r)) => match explain_at(l, r, path) {
+                (Ordering::Equal, _) => {}
+                decided => return decided,
+            },
+            (None, Some(_)) => return (Ordering::Less, Reason::LeftRanOut),
+            (Some(_), None) => return (Ordering::Greater, Reason::RightRanOut),
+            (None, None) => {}
+        }
+        path.pop();
+    }
+    (Ordering::Equal, Reason::Same)
+}
+
+impl Ord for Packet {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Packet::Int(left), Packet::Int(right)) => left.cmp(right),
+            // item by item, and then whichever runs out first is smaller
+            _ => self.as_slice().cmp(other.as_slice()),
+        }
+    }
+}
+
+impl PartialOrd for Packet {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// equal means in the same place in the order, so 2 and [[2]] are equal
+impl PartialEq for Packet {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Packet {}
+
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Packet::Int(n) => write!(f, "{}", n),
+            Packet::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+impl FromStr for Packet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            s,
+            position: 0,
+            open: Vec::new(),
+            expect: Expect::Value,
+            result: None,
+        };
+        parser.parse()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expect {
+    Value,
+    // straight after a [
+    ValueOrClose,
+    // straight after a value inside a list
+    CommaOrClose,
+}
+
+// one pass over the chars, keeping a stack of the lists that are still open rather than recursing
+struct Parser<'a> {
+    s: &'a str,
+    // a byte offset, which only gets turned into a column for errors
+    position: usize,
+    // where each open list's [ was, and what's in it so far
+    open: Vec<(usize, Vec<Packet>)>,
+    expect: Expect,
+    result: Option<Packet>,
+}
+
+impl Parser<'_> {
+    // 1-based, counting chars rather than bytes
+    fn column(&self, position: usize) -> usize {
+        self.s[..position].chars().count() + 1
+    }
+
+    fn error(&self, message: String) -> String {
+        format!("column {}: {}", self.column(self.position), message)
+    }
+
+    fn parse(&mut self) -> Result<Packet, String> {
+        let bytes = self.s.as_bytes();
+        while let Some(c) = self.s[self.position..].chars().next() {
+            if c.is_whitespace() {
+                self.position += c.len_utf8();
+                continue;
+            }
+            if self.result.is_some() {
+                Err(self.error(format!("unexpected {:?} after the end of the packet", c)))?
+            }
+            match (c, self.expect) {
+                ('[', Expect::Value | Expect::ValueOrClose) => {
+                    self.open.push((self.position, Vec::new()));
+                    self.expect = Expect::ValueOrClose;
+                    self.position += 1;
+                }
+                ('0'..='9', Expect::Value | Expect::ValueOrClose) => {
+                    let start = self.position;
+                    while bytes.get(self.position).is_some_and(|b| b.is_ascii_digit()) {
+                        self.position += 1;
+                    }
+                    let digits = &self.s[start..self.position];
+                    let n = digits.parse().map_err(|e| {
+                        self.position = start;
+                        self.error(format!("bad number {}: {}", digits, e))
+                    })?;
+                    self.finish(Packet::Int(n));
+                }
+                (']', Expect::ValueOrClose | Expect::CommaOrClose) => {
+                    // those are only expected while there's a list open
+                    let (_, items) = self.open.pop().unwrap();
+                    self.position += 1;
+                    self.finish(Packet::List(items));
+                }
+                (',', Expect::CommaOrClose) => {
+                    self.expect = Expect::Value;
+                    self.position += 1;
+                }
+                (c, Expect::Value) => Err(self.error(format!("expected a number or [: {:?}", c)))?,
+                (c, Expect::ValueOrClose) => {
+                    Err(self.error(format!("expected a number, [ or ]: {:?}", c)))?
+                }
+                (c, Expect::CommaOrClose) => Err(self.error(format!("expected , or ]: {:?}", c)))?,
+            }
+        }
+        match (self.result.take(), self.open.last()) {
+            (Some(result), _) => Ok(result),
+            (None, Some((start, _))) => Err(self.error(format!(
+                "expected ] to match column {}",
+                self.column(*start)
+            ))),
+            (None, None) => Err(self.error("expected a number or [".to_string())),
+        }
+    }
+
+    // a value that's complete goes in whatever list it's in, or is the whole packet
+    fn finish(&mut self, packet: Packet) {
+        match self.open.last_mut() {
+            Some((_, items)) => {
+                items.push(packet);
+                self.expect = Expect::CommaOrClose;
+            }
+            None => self.result = Some(packet),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Packet {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parsing() {
+        for s in ["[]", "3", "[1,[2,[3,[4,[5,6,7]]]],8,9]", "[[[]],10,[]]"] {
+            assert_eq!(s, parse(s).to_string());
+        }
+        assert_eq!("[1,[2]]", parse(" [ 1 , [2] ] ").to_string());
+        let error = |s: &str| s.parse::<Packet>().err().unwrap();
+        assert_eq!("column 6: expected ] to match column 4", error("[1,[2"));
+        assert_eq!("column 4: expected a number or [: ']'", error("[1,]"));
+        assert_eq!("column 4: expected , or ]: '2'", error("[1 2]"));
+        assert_eq!("column 2: expected a number, [ or ]: 'x'", error("[x]"));
+        assert_eq!(
+            "column 4: unexpected ']' after the end of the packet",
+            error("[1]]")
+        );
+        assert_eq!("column 1: expected a number or [", error(""));
+        assert_eq!(
+            "column 2: bad number 99999999999999999999: number too large to fit in target type",
+            error("[99999999999999999999]")
+        );
+        // columns count chars, not bytes
+        assert_eq!("column 4: expected , or ]: 'é'", error("[1\u{a0}é]"));
+        assert_eq!(
+            "column 8: expected ] to match column 6",
+            error("\u{a0}\u{a0}[1,[2")
+        );
+    }
+
+    #[test]
+    fn ordering() {
+        let less = |left: &str, right: &str| {
+            let (left, right) = (parse(left), parse(right));
+            assert_eq!(left.cmp(&right).reverse(), right.cmp(&left));
+            left < right
+        };
+        assert!(less("[1,1,3,1,1]", "[1,1,5,1,1]"));
+        assert!(less("[[1],[2,3,4]]", "[[1],4]"));
+        assert!(!less("[9]", "[[8,7,6]]"));
+        assert!(less("[[4,4],4,4]", "[[4,4],4,4,4]"));
+        assert!(less("[]", "[3]"));
+        assert!(!less("[[[]]]", "[[]]"));
+        assert_eq!(parse("2"), parse("[[2]]"));
+        assert_eq!(Ordering::Equal, parse("[2,[3]]").cmp(&parse("[[2],3]")));
+    }
+
+    #[test]
+    fn explanations() {
+        let explain = |left: &str, right: &str| {
+            let (left, right) = (parse(left), parse(right));
+            let explanation = explain(&left, &right);
+            assert_eq!(left.cmp(&right), explanation.ordering);
+            explanation
+        };
+        let explanation = explain("[[1],[2,3,4]]", "[[1],4]");
+        assert_eq!("at [1][0]: 2 < 4", explanation.to_string());
+        assert_eq!(
+            "[[1],[2,3,4]]\n      ^",
+            parse("[[1],[2,3,4]]").highlight(&explanation.path)
+        );
+        assert_eq!(
+            "[[1],4]\n     ^",
+            parse("[[1],4]").highlight(&explanation.path)
+        );
+        assert_eq!(
+            "[10,[],[[0,123]]]\n           ^^^",
+            parse("[10,[],[[0,123]]]").highlight(&[2, 0, 1])
+        );
+        assert_eq!(
+            "[10,[],[[0,123]]]\n    ^^",
+            parse("[10,[],[[0,123]]]").highlight(&[1])
+        );
+
+        let explanation = explain("[9]", "[[8,7,6]]");
+        assert_eq!("at [0][0]: 9 > 8", explanation.to_string());
+        assert_eq!("[9]\n ^", parse("[9]").highlight(&explanation.path));
+
+        let explanation = explain("[[4,4],4,4]", "[[4,4],4,4,4]");
+        assert_eq!("left ran out of items at [3]", explanation.to_string());
+        assert_eq!(
+            "[[4,4],4,4]\n          ^",
+            parse("[[4,4],4,4]").highlight(&explanation.path)
+        );
+        assert_eq!(
+            "right ran out of items at [0][0]",
+            explain("[[[]]]", "[[]]").to_string()
+        );
+        assert_eq!("at the top: 3 > 2", explain("3", "2").to_string());
+        assert_eq!(
+            "neither comes first",
+            explain("[2,[3]]", "[[2],3]").to_string()
+        );
+    }
+}
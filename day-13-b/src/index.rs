@@ -0,0 +1,105 @@
+use crate::packet::Packet;
+
+// packets sorted once, so where any other packet would go can be found by binary search
+#[derive(Debug, Clone)]
+pub struct PacketIndex {
+    packets: Vec<Packet>,
+}
+
+impl PacketIndex {
+    pub fn new(mut packets: Vec<Packet>) -> Self {
+        packets.sort();
+        Self { packets }
+    }
+
+    pub fn packets(&self) -> &[Packet] {
+        &self.packets
+    }
+
+    // how many packets would be in front of it if it were inserted, which goes after any it ties
+    // with
+    pub fn rank(&self, packet: &Packet) -> usize {
+        self.packets.partition_point(|p| p <= packet)
+    }
+
+    pub fn insert(&mut self, packet: Packet) {
+        let i = self.rank(&packet);
+        self.packets.insert(i, packet);
+    }
+
+    // how many packets come strictly after low and before high
+    pub fn count_between(&self, low: &Packet, high: &Packet) -> usize {
+        let before_high = self.packets.partition_point(|p| p < high);
+        before_high.saturating_sub(self.rank(low))
+    }
+
+    // where each divider would end up, counting from 1, if they were all inserted in order
+    pub fn positions(&self, dividers: &[Packet]) -> Vec<usize> {
+        dividers
+            .iter()
+            .enumerate()
+            .map(|(i, divider)| {
+                // any earlier divider that ties with this one stays in front of it too
+                let dividers_before = dividers
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, other)| *other < divider || (*j < i && *other == divider))
+                    .count();
+                self.rank(divider) + dividers_before + 1
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packets(s: &str) -> Vec<Packet> {
+        s.split_whitespace().map(|p| p.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn ranks() {
+        let index = PacketIndex::new(packets("[3] [1] [[2]] [2,1] [] 5"));
+        assert_eq!(
+            "[] [1] [[2]] [2,1] [3] 5",
+            index
+                .packets()
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        let rank = |s: &str| index.rank(&s.parse().unwrap());
+        assert_eq!(1, rank("[[]]"));
+        assert_eq!(3, rank("2"));
+        assert_eq!(6, rank("[6]"));
+        let between = |low: &str, high: &str| {
+            index.count_between(&low.parse().unwrap(), &high.parse().unwrap())
+        };
+        assert_eq!(2, between("[1]", "[3]"));
+        assert_eq!(5, between("[]", "[[[6]]]"));
+        assert_eq!(0, between("[3]", "[1]"));
+    }
+
+    #[test]
+    fn dividers_agree_with_inserting() {
+        let index = PacketIndex::new(packets("[3] [1] [[2]] [2,1] [] 5"));
+        let dividers = packets("[6] 2 [[2]] []");
+        let mut inserted = index.clone();
+        for divider in dividers.iter() {
+            inserted.insert(divider.clone());
+        }
+        // they tie with each other and the ones already there, so only the order they go in tells
+        // them apart
+        let positions = index.positions(&dividers);
+        assert_eq!(vec![10, 5, 6, 2], positions);
+        for (divider, position) in dividers.iter().zip(positions.iter()) {
+            assert_eq!(
+                divider.to_string(),
+                inserted.packets()[position - 1].to_string()
+            );
+        }
+    }
+}
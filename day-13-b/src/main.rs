@@ -1,119 +1,102 @@
-use serde_json::json;
+mod index;
+mod packet;
+
 use std::{
     cmp::Ordering,
+    env,
     error::Error,
     io::{self, BufRead, BufReader},
 };
 
+use index::PacketIndex;
+use packet::{Explanation, Packet};
+
 fn main() -> Result<(), Box<dyn Error>> {
     let mut r = BufReader::new(io::stdin());
-    do_it(&mut r)?;
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+    match args.as_slice() {
+        [] => {
+            do_it(&mut r, &["[[2]]".parse()?, "[[6]]".parse()?])?;
+        }
+        // any other dividers, as packets
+        ["dividers", dividers @ ..] => {
+            let dividers = dividers
+                .iter()
+                .map(|divider| divider.parse())
+                .collect::<Result<Vec<_>, _>>()?;
+            do_it(&mut r, &dividers)?;
+        }
+        // every packet in order, with any others added in where they go
+        ["sorted", extra @ ..] => {
+            let mut index = PacketIndex::new(parse_packets(&mut r)?);
+            for packet in extra.iter() {
+                index.insert(packet.parse()?);
+            }
+            for packet in index.packets().iter() {
+                println!("{}", packet);
+            }
+        }
+        // how many packets come in between two others
+        ["between", low, high] => {
+            let index = PacketIndex::new(parse_packets(&mut r)?);
+            println!(
+                "between = {}",
+                index.count_between(&low.parse()?, &high.parse()?)
+            );
+        }
+        // why a pair, numbered from 1, is in the order it is
+        ["explain", index] => {
+            explain(&mut r, index.parse()?)?;
+        }
+        _ => Err(format!("unrecognized arguments: {:?}", args))?,
+    };
     Ok(())
 }
 
-fn do_it(r: &mut impl std::io::Read) -> Result<usize, Box<dyn Error>> {
-    #[derive(PartialEq, Debug)]
-    enum LineMarker {
-        Normal,
-        Special,
-    }
-
-    let mut lines = BufReader::new(r)
-        .lines()
-        // get rid of any broken lines
-        .collect::<Result<Vec<_>, _>>()?
-        .iter()
-        // get rid of empty lines
-        .filter(|line| !line.trim().is_empty())
-        // parse as json
-        .map(|line| serde_json::from_str::<serde_json::Value>(line))
-        // get rid of any parse errors
-        .collect::<Result<Vec<_>, _>>()?
-        .iter()
-        // add line markers
-        .map(|value| (LineMarker::Normal, value.clone()))
-        // .map(|value| value.clone())
-        .collect::<Vec<_>>();
-    if lines.len() % 2 != 0 {
-        Err(format!(
-            "expected an even number of lines, got {}",
-            lines.len()
-        ))?;
-    }
-
-    lines.push((LineMarker::Special, json!([[2]])));
-    lines.push((LineMarker::Special, json!([[6]])));
-
-    lines.sort_by(|(_, a), (_, b)| compare_pair(a, b).unwrap());
-
-    for line in lines.iter() {
-        println!("{:?}", line);
-    }
-
-    let special_line_indices = lines
-        .iter()
-        .enumerate()
-        .filter(|(i, (marker, _))| *marker == LineMarker::Special)
-        .map(|(i, _)| i + 1)
-        .collect::<Vec<_>>();
-    println!("special line indices = {:?}", special_line_indices);
-    let result = special_line_indices[0] * special_line_indices[1];
+// the product of where each divider ends up once they're added to the packets, counting from 1
+fn do_it(r: &mut impl std::io::Read, dividers: &[Packet]) -> Result<usize, Box<dyn Error>> {
+    let index = PacketIndex::new(parse_packets(r)?);
+    let positions = index.positions(dividers);
+    println!("divider positions = {:?}", positions);
+    let result = positions.iter().product();
     println!("result = {}", result);
     Ok(result)
 }
 
-fn compare_pair(
-    left: &serde_json::Value,
-    right: &serde_json::Value,
-) -> Result<std::cmp::Ordering, Box<dyn Error>> {
-    if left.is_number() && right.is_number() {
-        let left = assert_number(left)?;
-        let right = assert_number(right)?;
-        Ok(left.cmp(&right))
-    } else if left.is_array() && right.is_array() {
-        let left = assert_array(left)?;
-        let right = assert_array(right)?;
-        let mut i = 0;
-        loop {
-            if i >= left.len() && i >= right.len() {
-                return Ok(Ordering::Equal);
-            }
-            if i >= left.len() {
-                return Ok(Ordering::Less);
-            }
-            if i >= right.len() {
-                return Ok(Ordering::Greater);
-            }
-            let result = compare_pair(&left[i], &right[i])?;
-            if result == Ordering::Less || result == Ordering::Greater {
-                return Ok(result);
-            }
-            i += 1;
-        }
-    } else if left.is_number() && right.is_array() {
-        let left = &json!([assert_number(left)?]);
-        compare_pair(left, right)
-    } else if left.is_array() && right.is_number() {
-        let right = &json!([assert_number(right)?]);
-        compare_pair(left, right)
-    } else {
-        Err(format!(
-            "types aren't numbers or arrays, left = {:?}, right = {:?}",
-            left, right
-        ))?
+fn explain(r: &mut impl std::io::Read, index: usize) -> Result<Explanation, Box<dyn Error>> {
+    let packets = parse_packets(r)?;
+    let pairs = packets.len() / 2;
+    if index == 0 || index > pairs {
+        Err(format!("no pair {}, there are {}", index, pairs))?
     }
+    let (left, right) = (&packets[index * 2 - 2], &packets[index * 2 - 1]);
+    let explanation = packet::explain(left, right);
+    let order = match explanation.ordering {
+        Ordering::Less => "the right order",
+        Ordering::Equal => "neither order",
+        Ordering::Greater => "the wrong order",
+    };
+    println!("pair {} is in {}, {}", index, order, explanation);
+    println!("{}", left.highlight(&explanation.path));
+    println!("{}", right.highlight(&explanation.path));
+    Ok(explanation)
 }
 
-fn assert_number(value: &serde_json::Value) -> Result<i64, Box<dyn Error>> {
-    Ok(value
-        .as_i64()
-        .ok_or(format!("expected number: {}", value))?)
-}
-
-fn assert_array(value: &serde_json::Value) -> Result<&Vec<serde_json::Value>, Box<dyn Error>> {
-    Ok(value
-        .as_array()
-        .ok_or(format!("expected array: {}", value))?)
+// one packet per line, with blank lines in between pairs
+fn parse_packets(r: &mut impl std::io::Read) -> Result<Vec<Packet>, Box<dyn Error>> {
+    let mut results = Vec::new();
+    for (i, line) in BufReader::new(r).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let packet = line
+            .parse::<Packet>()
+            .map_err(|e| format!("line {}: {}", i + 1, e))?;
+        results.push(packet);
+    }
+    Ok(results)
 }
 
 #[cfg(test)]
@@ -122,12 +105,7 @@ mod tests {
 
     use super::*;
 
-    #[test]
-    fn sample() -> Result<(), Box<dyn Error>> {
-        assert_eq!(
-            140,
-            do_it(
-                &mut r"[1,1,3,1,1]
+    const SAMPLE: &str = r"[1,1,3,1,1]
 [1,1,5,1,1]
 
 [[1],[2,3,4]]
@@ -149,10 +127,35 @@ mod tests {
 [[]]
 
 [1,[2,[3,[4,[5,6,7]]]],8,9]
-[1,[2,[3,[4,[5,6,0]]]],8,9]"
-                    .as_bytes(),
-            )?
+[1,[2,[3,[4,[5,6,0]]]],8,9]";
+
+    #[test]
+    fn sample() -> Result<(), Box<dyn Error>> {
+        let dividers = ["[[2]]".parse()?, "[[6]]".parse()?];
+        assert_eq!(140, do_it(&mut SAMPLE.as_bytes(), &dividers)?);
+        // [2] ties with [[2]], and goes in front of it
+        let dividers = ["[2]".parse()?, "[[2]]".parse()?, "[]".parse()?];
+        assert_eq!(2 * 11 * 12, do_it(&mut SAMPLE.as_bytes(), &dividers)?);
+        Ok(())
+    }
+
+    #[test]
+    fn bad_packet() {
+        assert_eq!(
+            "line 2: column 4: expected a number or [: ']'",
+            do_it(&mut "[1]\n[1,]".as_bytes(), &[])
+                .err()
+                .unwrap()
+                .to_string()
         );
+    }
+
+    #[test]
+    fn explained() -> Result<(), Box<dyn Error>> {
+        let explanation = explain(&mut SAMPLE.as_bytes(), 8)?;
+        assert_eq!("at [1][1][1][1][2]: 7 > 0", explanation.to_string());
+        assert!(explain(&mut SAMPLE.as_bytes(), 9).is_err());
+        assert!(explain(&mut SAMPLE.as_bytes(), 0).is_err());
         Ok(())
     }
 }
@@ -1,9 +1,14 @@
+mod sand;
+
 use std::{
+    env,
     error::Error,
     io::{self, BufRead, BufReader},
 };
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+use sand::{Cave, Material};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Point {
     x: i32,
     y: i32,
@@ -15,6 +20,64 @@ struct Line {
     b: Point,
 }
 
+impl Line {
+    // every point from a to b inclusive, at any angle, by bresenham's algorithm
+    pub fn points(&self) -> Vec<Point> {
+        let dx = (self.b.x - self.a.x).abs();
+        let dy = -(self.b.y - self.a.y).abs();
+        let sx = (self.b.x - self.a.x).signum();
+        let sy = (self.b.y - self.a.y).signum();
+        // how far off the true line the next point would be, scaled up to stay whole
+        let mut error = dx + dy;
+        let mut p = self.a;
+        let mut results = vec![p];
+        while p != self.b {
+            // whether stepping across, down, or both keeps closest to it
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                p.x += sx;
+            }
+            if e2 <= dx {
+                error += dx;
+                p.y += sy;
+            }
+            results.push(p);
+        }
+        results
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ShapeKind {
+    Line(Line),
+    // filled in between two opposite corners
+    Rectangle(Point, Point),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Shape {
+    material: Material,
+    kind: ShapeKind,
+}
+
+impl Shape {
+    pub fn points(&self) -> Vec<Point> {
+        match self.kind {
+            ShapeKind::Line(line) => line.points(),
+            ShapeKind::Rectangle(a, b) => {
+                let mut results = Vec::new();
+                for y in a.y.min(b.y)..=a.y.max(b.y) {
+                    for x in a.x.min(b.x)..=a.x.max(b.x) {
+                        results.push(Point { x, y });
+                    }
+                }
+                results
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 struct BoundingRectangle {
     min: Point,
@@ -53,119 +116,111 @@ impl BoundingRectangle {
 
 fn main() -> Result<(), Box<dyn Error>> {
     let mut r = BufReader::new(io::stdin());
-    do_it(&mut r)?;
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+    let (sources, args) = parse_flags(&args)?;
+    match args {
+        [] => {
+            do_it(&mut r, &sources)?;
+        }
+        _ => Err(format!("unrecognized arguments: {:?}", args))?,
+    };
     Ok(())
 }
 
-fn do_it(r: &mut impl std::io::Read) -> Result<usize, Box<dyn Error>> {
-    let lines = BufReader::new(r)
-        .lines()
-        .collect::<Result<Vec<_>, _>>()?
-        .iter()
-        .map(|line| parse_line(line))
-        .collect::<Result<Vec<_>, _>>()?;
-    let lines = lines
-        .iter()
-        .flatten()
-        .map(|line| line.clone())
-        .collect::<Vec<_>>();
-    for line in lines.iter() {
-        println!("line = {:?}", line);
-    }
-
-    let bounds = {
-        let mut result =
-            BoundingRectangle::new(lines.iter().flat_map(|line| vec![line.a, line.b]))?;
-        result.min.y = 0;
-        result
-    };
-    println!("bounds = {:?}", bounds);
-
-    let mut sand_at_rest = Vec::new();
-    let mut sand_in_motion = None;
+// the sources, and the rest of the arguments
+type Flags<'a, 'b> = (Vec<Point>, &'b [&'a str]);
 
-    let mut tick = 0;
+// any number of "--source x,y" before the command, or just 500,0 if there aren't any
+fn parse_flags<'a, 'b>(args: &'b [&'a str]) -> Result<Flags<'a, 'b>, Box<dyn Error>> {
+    let mut sources = Vec::new();
+    let mut args = args;
     loop {
-        // generate new sand
-        if sand_in_motion.is_none() {
-            sand_in_motion = Some(Point { x: 500, y: 0 });
-        }
-
-        // look at the locations we can move sand to and find the next location, or none if no new location is possible
-        let mut new_location = None;
-        for proposed_new_point in [
-            Point {
-                x: sand_in_motion.unwrap().x,
-                y: sand_in_motion.unwrap().y + 1,
-            },
-            Point {
-                x: sand_in_motion.unwrap().x - 1,
-                y: sand_in_motion.unwrap().y + 1,
-            },
-            Point {
-                x: sand_in_motion.unwrap().x + 1,
-                y: sand_in_motion.unwrap().y + 1,
-            },
-        ] {
-            if !point_collide_environment(&proposed_new_point, lines.iter(), sand_at_rest.iter())? {
-                new_location = Some(proposed_new_point);
-                break;
+        args = match args {
+            ["--source", value, rest @ ..] => {
+                sources.push(parse_point(value)?);
+                rest
             }
-        }
+            _ => break,
+        };
+    }
+    if sources.is_empty() {
+        sources.push(Point { x: 500, y: 0 });
+    }
+    Ok((sources, args))
+}
 
-        // either we moved the sand, or we can't and it stops here
-        if let Some(new_location) = new_location {
-            // check against the bounds
-            if bounds_contains_point(&bounds, &new_location) {
-                // still in bounds, we can move here
-                sand_in_motion = Some(new_location);
-            } else {
-                // out of bounds, we're done
-                break;
-            }
-        } else {
-            // no new location found, we're done with this one
-            sand_at_rest.push(sand_in_motion.unwrap());
-            sand_in_motion = None;
-        }
+fn do_it(r: &mut impl std::io::Read, sources: &[Point]) -> Result<usize, Box<dyn Error>> {
+    let shapes = parse_lines(r)?;
 
-        if tick % 100 == 0 {
-            println!("tick = {}", tick);
-            draw_environment(&bounds, sand_in_motion, lines.iter(), sand_at_rest.iter());
-            println!("");
-        }
-        tick += 1;
-    }
+    let mut cave = Cave::new(&shapes, None, sources)?;
+    let total = cave.fill();
 
     println!("final environment");
-    println!("tick = {}", tick);
-    draw_environment(&bounds, sand_in_motion, lines.iter(), sand_at_rest.iter());
-    println!("");
+    print!("{}", cave.draw());
+    println!();
 
-    println!("total sand dropped = {}", sand_at_rest.len());
-    Ok(sand_at_rest.len())
+    println!("total sand dropped = {}", total);
+    Ok(total)
 }
 
-fn parse_line(s: &str) -> Result<Vec<Line>, Box<dyn Error>> {
+fn parse_lines(r: &mut impl std::io::Read) -> Result<Vec<Shape>, Box<dyn Error>> {
+    let mut shapes = Vec::new();
+    for (i, line) in BufReader::new(r).lines().enumerate() {
+        let parsed = parse_line(&line?).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        shapes.extend(parsed);
+    }
+    for shape in shapes.iter() {
+        println!("shape = {:?}", shape);
+    }
+    Ok(shapes)
+}
+
+// "[material:] [rect] x,y -> x,y ...", where the material is rock unless it says otherwise, a
+// rectangle has just its two corners, and anything after a # is ignored
+fn parse_line(s: &str) -> Result<Vec<Shape>, Box<dyn Error>> {
+    let s = s.split('#').next().unwrap().trim();
+    if s.is_empty() {
+        return Ok(Vec::new());
+    }
+    let (material, s) = match s.split_once(':') {
+        Some((material, rest)) => (material.parse()?, rest.trim()),
+        None => (Material::Rock, s),
+    };
+    let (rectangle, s) = match s.strip_prefix("rect ") {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
     let points = s
         .split("->")
         .map(parse_point)
         .collect::<Result<Vec<_>, _>>()?;
+    if rectangle {
+        if points.len() != 2 {
+            Err(format!("expected a rectangle's two corners, got {}", s))?
+        }
+        return Ok(vec![Shape {
+            material,
+            kind: ShapeKind::Rectangle(points[0], points[1]),
+        }]);
+    }
     if points.len() < 2 {
         Err(format!("expected at least two points, got {}", s))?
     }
-    let mut results = Vec::new();
-    for i in 0..(points.len() - 1) {
-        results.push(Line {
-            a: points[i],
-            b: points[i + 1],
-        });
-    }
-    Ok(results)
+    Ok(points
+        .windows(2)
+        .map(|pair| Shape {
+            material,
+            kind: ShapeKind::Line(Line {
+                a: pair[0],
+                b: pair[1],
+            }),
+        })
+        .collect())
 }
 
 fn parse_point(s: &str) -> Result<Point, Box<dyn Error>> {
-    let parts = s.trim().split(",").collect::<Vec<_>>();
+    let parts = s.trim().split(',').collect::<Vec<_>>();
     if parts.len() == 2 {
         Ok(Point {
             x: parts[0].parse()?,
@@ -179,101 +234,6 @@ fn parse_point(s: &str) -> Result<Point, Box<dyn Error>> {
     }
 }
 
-fn draw_environment<'a, LineI, PointI>(
-    bounds: &BoundingRectangle,
-    point: Option<Point>,
-    lines: LineI,
-    other_points: PointI,
-) where
-    LineI: Iterator<Item = &'a Line>,
-    PointI: Iterator<Item = &'a Point>,
-{
-    let width = bounds.max.x - bounds.min.x + 1;
-    let height = bounds.max.y - bounds.min.y + 1;
-    let mut data = Vec::new();
-    data.resize((width * height) as usize, '.');
-    for line in lines {
-        let mut p = line.a;
-        while p != line.b {
-            data[((p.x - bounds.min.x) + (p.y - bounds.min.y) * width) as usize] = '#';
-            if p.x < line.b.x {
-                p.x += 1;
-            } else if p.x > line.b.x {
-                p.x -= 1;
-            }
-            if p.y < line.b.y {
-                p.y += 1;
-            } else if p.y > line.b.y {
-                p.y -= 1;
-            }
-        }
-    }
-    for p in other_points {
-        data[((p.x - bounds.min.x) + (p.y - bounds.min.y) * width) as usize] = 'o';
-    }
-    if let Some(point) = point {
-        data[((point.x - bounds.min.x) + (point.y - bounds.min.y) * width) as usize] = 'O';
-    }
-    for y in 0..height {
-        for x in 0..width {
-            print!("{}", data[(x + y * width) as usize]);
-        }
-        println!("");
-    }
-}
-
-fn point_collide_environment<'a, LineI, PointI>(
-    point: &Point,
-    lines: LineI,
-    other_points: PointI,
-) -> Result<bool, String>
-where
-    LineI: Iterator<Item = &'a Line>,
-    PointI: Iterator<Item = &'a Point>,
-{
-    for line in lines {
-        let result = point_collide_line(point, &line)?;
-        if result {
-            return Ok(true);
-        }
-    }
-    for other_point in other_points {
-        let result = point_collide_point(point, &other_point);
-        if result {
-            return Ok(true);
-        }
-    }
-    Ok(false)
-}
-
-fn point_collide_point(point: &Point, other: &Point) -> bool {
-    point.x == other.x && point.y == other.y
-}
-
-fn point_collide_line(point: &Point, line: &Line) -> Result<bool, String> {
-    if line.a.x == line.b.x {
-        let min = std::cmp::min(line.a.y, line.b.y);
-        let max = std::cmp::max(line.a.y, line.b.y);
-        Ok(point.x == line.a.x && point.y >= min && point.y <= max)
-    } else if line.a.y == line.b.y {
-        let min = std::cmp::min(line.a.x, line.b.x);
-        let max = std::cmp::max(line.a.x, line.b.x);
-        Ok(point.y == line.a.y && point.x >= min && point.x <= max)
-    } else {
-        Err(format!(
-            "line is not perfectly horizontal or vertical: {:?}",
-            line
-        ))
-    }
-}
-
-fn bounds_contains_point(bounds: &BoundingRectangle, point: &Point) -> bool {
-    point.x >= bounds.min.x
-        && point.y <= bounds.max.x
-        && point.y >= bounds.min.y
-        && point.y <= bounds.max.y
-}
-
 #[cfg(test)]
 mod tests {
     use std::error::Error;
@@ -288,8 +248,78 @@ mod tests {
                 &mut r"498,4 -> 498,6 -> 496,6
 503,4 -> 502,4 -> 502,9 -> 494,9"
                     .as_bytes(),
+                &[Point { x: 500, y: 0 }],
             )?
         );
         Ok(())
     }
+
+    #[test]
+    fn bresenham() {
+        let points = |a: (i32, i32), b: (i32, i32)| {
+            Line {
+                a: Point { x: a.0, y: a.1 },
+                b: Point { x: b.0, y: b.1 },
+            }
+            .points()
+            .iter()
+            .map(|p| (p.x, p.y))
+            .collect::<Vec<_>>()
+        };
+        // the same cases as day 8's viewshed lines, which share this formulation
+        assert_eq!(
+            vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)],
+            points((0, 0), (4, 2))
+        );
+        assert_eq!(vec![(2, 3), (1, 2), (1, 1), (0, 0)], points((2, 3), (0, 0)));
+        // steep
+        assert_eq!(
+            vec![(0, 0), (0, 1), (1, 2), (1, 3), (1, 4)],
+            points((0, 0), (1, 4))
+        );
+        assert_eq!(
+            vec![(1, 4), (1, 3), (0, 2), (0, 1), (0, 0)],
+            points((1, 4), (0, 0))
+        );
+        // negative slopes
+        assert_eq!(
+            vec![(3, 0), (2, -1), (1, -1), (0, -2)],
+            points((3, 0), (0, -2))
+        );
+        assert_eq!(
+            vec![(0, -2), (1, -1), (2, -1), (3, 0)],
+            points((0, -2), (3, 0))
+        );
+        assert_eq!(vec![(0, 0), (1, 1), (2, 1), (3, 2)], points((0, 0), (3, 2)));
+        assert_eq!(vec![(2, 3), (2, 2), (1, 1), (1, 0)], points((2, 3), (1, 0)));
+        assert_eq!(vec![(5, 5), (4, 4), (3, 3)], points((5, 5), (3, 3)));
+    }
+
+    #[test]
+    fn scan_format() -> Result<(), Box<dyn Error>> {
+        let shapes = parse_lines(
+            &mut r"# a comment, and then a blank line
+
+498,4 -> 500,6 # a diagonal
+water left: 490,0 -> 491,0
+platform: rect 490,2 -> 491,3"
+                .as_bytes(),
+        )?;
+        assert_eq!(3, shapes.len());
+        assert_eq!(3, shapes[0].points().len());
+        assert_eq!(Material::Water(-1), shapes[1].material);
+        assert_eq!(Material::Platform, shapes[2].material);
+        assert_eq!(4, shapes[2].points().len());
+
+        let error = |s: &str| parse_lines(&mut s.as_bytes()).err().unwrap().to_string();
+        assert_eq!(
+            "line 3: expected a rectangle's two corners, got 1,1",
+            error("0,0 -> 1,1\n\nrect 1,1")
+        );
+        assert_eq!(
+            "line 2: unknown material: \"lava\"",
+            error("# fine\nlava: 0,0 -> 1,1")
+        );
+        Ok(())
+    }
 }
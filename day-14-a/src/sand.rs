@@ -0,0 +1,360 @@
+use std::{collections::HashMap, str::FromStr};
+
+use crate::{BoundingRectangle, Point, Shape};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Material {
+    Rock,
+    Sand,
+    // sand falls through it as if it weren't there, but can still come to rest in it
+    Platform,
+    // carries sand that can't fall any further sideways, one way or the other
+    Water(i32),
+}
+
+impl Material {
+    pub fn symbol(&self) -> char {
+        match self {
+            Material::Rock => '#',
+            Material::Sand => 'o',
+            Material::Platform => '-',
+            Material::Water(_) => '~',
+        }
+    }
+
+    // whether sand can't go into it
+    pub fn solid(&self) -> bool {
+        matches!(self, Material::Rock | Material::Sand)
+    }
+}
+
+impl FromStr for Material {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["rock"] => Ok(Material::Rock),
+            ["platform"] => Ok(Material::Platform),
+            ["water"] | ["water", "right"] => Ok(Material::Water(1)),
+            ["water", "left"] => Ok(Material::Water(-1)),
+            _ => Err(format!("unknown material: {:?}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Rested(Point),
+    // fell past the lowest rock with no floor to stop it
+    Abyss,
+    // the source is already covered
+    Blocked,
+}
+
+struct Source {
+    at: Point,
+    // where the last grain from here fell, up to just above where it came to rest, so the next one
+    // can carry on from there instead of starting again from the top
+    path: Vec<Point>,
+}
+
+pub struct Cave {
+    // only what's been filled in, so the floor can be as wide as it needs to be
+    cells: HashMap<Point, Material>,
+    bottom: i32,
+    // how far below the lowest rock there's an endless floor, if there is one
+    floor: Option<i32>,
+    sources: Vec<Source>,
+    settled: usize,
+}
+
+impl Cave {
+    pub fn new(shapes: &[Shape], floor: Option<i32>, sources: &[Point]) -> Result<Cave, String> {
+        // anything later in the scan goes over the top of anything earlier
+        let mut cells = HashMap::new();
+        for shape in shapes.iter() {
+            for p in shape.points() {
+                cells.insert(p, shape.material);
+            }
+        }
+        // nothing below here can stop sand, whatever it's made of
+        let bottom = cells
+            .keys()
+            .map(|p| p.y)
+            .max()
+            .ok_or("expected at least one shape")?;
+        if sources.is_empty() {
+            Err("expected at least one source of sand")?
+        }
+        Ok(Cave {
+            cells,
+            bottom,
+            floor: floor.map(|gap| bottom + gap),
+            sources: sources
+                .iter()
+                .map(|at| Source {
+                    at: *at,
+                    path: Vec::new(),
+                })
+                .collect(),
+            settled: 0,
+        })
+    }
+
+    pub fn get(&self, p: Point) -> Option<Material> {
+        match self.floor {
+            Some(floor) if p.y >= floor => Some(Material::Rock),
+            _ => self.cells.get(&p).copied(),
+        }
+    }
+
+    fn blocked(&self, p: Point) -> bool {
+        self.get(p).is_some_and(|material| material.solid())
+    }
+
+    // drops one grain from the given source and follows it until it stops one way or another
+    pub fn drop_from(&mut self, source: usize) -> Outcome {
+        let at = self.sources[source].at;
+        if self.sources[source].path.is_empty() {
+            if self.blocked(at) {
+                return Outcome::Blocked;
+            }
+            self.sources[source].path.push(at);
+        }
+        loop {
+            let p = *self.sources[source].path.last().unwrap();
+            if self.floor.is_none() && p.y >= self.bottom {
+                // and so will every grain after it that gets this far
+                return Outcome::Abyss;
+            }
+            let next = [
+                Point { x: p.x, y: p.y + 1 },
+                Point {
+                    x: p.x - 1,
+                    y: p.y + 1,
+                },
+                Point {
+                    x: p.x + 1,
+                    y: p.y + 1,
+                },
+            ]
+            .into_iter()
+            .find(|next| !self.blocked(*next));
+            match next {
+                Some(next) => self.sources[source].path.push(next),
+                None => {
+                    // water carries it along instead of letting it settle, unless that's into
+                    // water going the other way, which would only carry it back again
+                    if let Some(Material::Water(flow)) = self.get(p) {
+                        let side = Point {
+                            x: p.x + flow,
+                            y: p.y,
+                        };
+                        let carried = match self.get(side) {
+                            Some(Material::Water(other)) => other == flow,
+                            Some(material) => !material.solid(),
+                            None => true,
+                        };
+                        if carried {
+                            self.sources[source].path.push(side);
+                            continue;
+                        }
+                    }
+                    self.sources[source].path.pop();
+                    self.cells.insert(p, Material::Sand);
+                    self.settled += 1;
+                    // other sources' grains can't get past here any more
+                    for (i, other) in self.sources.iter_mut().enumerate() {
+                        if i != source {
+                            if let Some(j) = other.path.iter().position(|q| *q == p) {
+                                other.path.truncate(j);
+                            }
+                        }
+                    }
+                    return Outcome::Rested(p);
+                }
+            }
+        }
+    }
+
+    // takes turns dropping a grain from each source until none of them can settle any more, and
+    // returns how many did
+    pub fn fill(&mut self) -> usize {
+        loop {
+            let mut progress = false;
+            for source in 0..self.sources.len() {
+                if let Outcome::Rested(_) = self.drop_from(source) {
+                    progress = true;
+                }
+            }
+            if !progress {
+                return self.settled;
+            }
+            if self.settled.is_multiple_of(1000) {
+                println!("total so far = {}", self.settled);
+            }
+        }
+    }
+
+    pub fn draw(&self) -> String {
+        let points = self.cells.keys().chain(self.sources.iter().map(|s| &s.at));
+        let mut bounds = BoundingRectangle::new(points.copied()).unwrap();
+        if let Some(floor) = self.floor {
+            bounds.max.y = floor;
+        }
+        let mut result = String::new();
+        for y in bounds.min.y..=bounds.max.y {
+            for x in bounds.min.x..=bounds.max.x {
+                let p = Point { x, y };
+                result.push(match self.get(p) {
+                    Some(material) if material.solid() => material.symbol(),
+                    _ if self.sources.iter().any(|s| s.at == p) => '+',
+                    Some(material) => material.symbol(),
+                    None => '.',
+                });
+            }
+            result.push('\n');
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Line, ShapeKind};
+
+    fn line(a: (i32, i32), b: (i32, i32)) -> Shape {
+        shape(Material::Rock, a, b)
+    }
+
+    fn shape(material: Material, a: (i32, i32), b: (i32, i32)) -> Shape {
+        Shape {
+            material,
+            kind: ShapeKind::Line(Line {
+                a: Point { x: a.0, y: a.1 },
+                b: Point { x: b.0, y: b.1 },
+            }),
+        }
+    }
+
+    #[test]
+    fn cup() {
+        let lines = [
+            line((0, 0), (0, 2)),
+            line((0, 2), (4, 2)),
+            line((4, 2), (4, 0)),
+        ];
+        let mut cave = Cave::new(&lines, None, &[Point { x: 2, y: -2 }]).unwrap();
+        assert_eq!(Outcome::Rested(Point { x: 2, y: 1 }), cave.drop_from(0));
+        assert_eq!(Outcome::Rested(Point { x: 1, y: 1 }), cave.drop_from(0));
+        assert_eq!(10, cave.fill());
+        assert_eq!(Outcome::Blocked, cave.drop_from(0));
+        assert_eq!("..o..\n.ooo.\n#ooo#\n#ooo#\n#####\n", cave.draw());
+    }
+
+    #[test]
+    fn abyss() {
+        let lines = [line((0, 3), (3, 3))];
+        let mut cave = Cave::new(&lines, None, &[Point { x: 1, y: 0 }]).unwrap();
+        assert_eq!(1, cave.fill());
+        assert_eq!(Outcome::Abyss, cave.drop_from(0));
+        assert_eq!(".+..\n....\n.o..\n####\n", cave.draw());
+    }
+
+    #[test]
+    fn floor_and_sources() {
+        // two sources whose piles run into each other on the floor
+        let lines = [line((0, 0), (0, 0))];
+        let sources = [Point { x: 10, y: 0 }, Point { x: 12, y: 0 }];
+        let mut cave = Cave::new(&lines, Some(2), &sources).unwrap();
+        assert_eq!(7, cave.fill());
+        assert_eq!(
+            "#.........o.o.\n.........ooooo\n##############\n",
+            cave.draw()
+        );
+    }
+
+    // every grain from the top, the slow way
+    fn from_the_top(lines: &[Shape], floor: Option<i32>, sources: &[Point]) -> Cave {
+        let mut cave = Cave::new(lines, floor, sources).unwrap();
+        loop {
+            let mut progress = false;
+            for source in 0..sources.len() {
+                cave.sources[source].path.clear();
+                progress |= matches!(cave.drop_from(source), Outcome::Rested(_));
+            }
+            if !progress {
+                return cave;
+            }
+        }
+    }
+
+    #[test]
+    fn same_as_from_the_top() {
+        let lines = [
+            line((498, 4), (498, 6)),
+            line((498, 6), (496, 6)),
+            line((503, 4), (502, 4)),
+            line((502, 4), (502, 9)),
+            line((502, 9), (494, 9)),
+        ];
+        for floor in [None, Some(2), Some(5)] {
+            for sources in [
+                vec![Point { x: 500, y: 0 }],
+                vec![Point { x: 500, y: 0 }, Point { x: 497, y: 2 }],
+                vec![
+                    Point { x: 503, y: 1 },
+                    Point { x: 499, y: -3 },
+                    Point { x: 501, y: 5 },
+                ],
+            ] {
+                let mut cave = Cave::new(&lines, floor, &sources).unwrap();
+                cave.fill();
+                let expected = from_the_top(&lines, floor, &sources);
+                assert_eq!(expected.settled, cave.settled);
+                assert_eq!(expected.draw(), cave.draw());
+            }
+        }
+    }
+
+    #[test]
+    fn platforms_and_water() {
+        // sand goes straight through the platform and piles up on the rock under it
+        let shapes = [
+            shape(Material::Platform, (-1, 2), (3, 2)),
+            line((-2, 3), (4, 3)),
+        ];
+        let mut cave = Cave::new(&shapes, None, &[Point { x: 1, y: 0 }]).unwrap();
+        assert_eq!(Outcome::Rested(Point { x: 1, y: 2 }), cave.drop_from(0));
+        assert_eq!(Outcome::Rested(Point { x: 0, y: 2 }), cave.drop_from(0));
+        assert_eq!(Outcome::Rested(Point { x: 2, y: 2 }), cave.drop_from(0));
+        assert_eq!(Outcome::Rested(Point { x: 1, y: 1 }), cave.drop_from(0));
+        assert_eq!("...+...\n...o...\n.-ooo-.\n#######\n", cave.draw());
+
+        // the water carries it right along the rock until it falls off the end
+        let shapes = [
+            shape(Material::Water(1), (0, 2), (2, 2)),
+            line((-1, 3), (2, 3)),
+            line((0, 5), (6, 5)),
+        ];
+        let mut cave = Cave::new(&shapes, None, &[Point { x: 0, y: 0 }]).unwrap();
+        assert_eq!(Outcome::Rested(Point { x: 3, y: 4 }), cave.drop_from(0));
+        // until it's all filled in and they start going off the other end
+        assert_eq!(10, cave.fill());
+        assert_eq!(
+            ".+......\n........\n.oooo...\n####oo..\n...oooo.\n.#######\n",
+            cave.draw()
+        );
+
+        // water flowing into water flowing the other way leaves it where it is
+        let shapes = [
+            shape(Material::Water(1), (0, 1), (1, 1)),
+            shape(Material::Water(-1), (2, 1), (3, 1)),
+            line((0, 2), (3, 2)),
+        ];
+        let mut cave = Cave::new(&shapes, None, &[Point { x: 1, y: 0 }]).unwrap();
+        assert_eq!(Outcome::Rested(Point { x: 1, y: 1 }), cave.drop_from(0));
+        assert_eq!(".+..\n~o~~\n####\n", cave.draw());
+    }
+}
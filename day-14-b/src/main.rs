@@ -1,9 +1,16 @@
+mod reachability;
+mod sand;
+
 use std::{
+    env,
     error::Error,
     io::{self, BufRead, BufReader},
+    time::Instant,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+use sand::{Cave, Material};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Point {
     x: i32,
     y: i32,
@@ -15,6 +22,64 @@ struct Line {
     b: Point,
 }
 
+impl Line {
+    // every point from a to b inclusive, at any angle, by bresenham's algorithm
+    pub fn points(&self) -> Vec<Point> {
+        let dx = (self.b.x - self.a.x).abs();
+        let dy = -(self.b.y - self.a.y).abs();
+        let sx = (self.b.x - self.a.x).signum();
+        let sy = (self.b.y - self.a.y).signum();
+        // how far off the true line the next point would be, scaled up to stay whole
+        let mut error = dx + dy;
+        let mut p = self.a;
+        let mut results = vec![p];
+        while p != self.b {
+            // whether stepping across, down, or both keeps closest to it
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                p.x += sx;
+            }
+            if e2 <= dx {
+                error += dx;
+                p.y += sy;
+            }
+            results.push(p);
+        }
+        results
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ShapeKind {
+    Line(Line),
+    // filled in between two opposite corners
+    Rectangle(Point, Point),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Shape {
+    material: Material,
+    kind: ShapeKind,
+}
+
+impl Shape {
+    pub fn points(&self) -> Vec<Point> {
+        match self.kind {
+            ShapeKind::Line(line) => line.points(),
+            ShapeKind::Rectangle(a, b) => {
+                let mut results = Vec::new();
+                for y in a.y.min(b.y)..=a.y.max(b.y) {
+                    for x in a.x.min(b.x)..=a.x.max(b.x) {
+                        results.push(Point { x, y });
+                    }
+                }
+                results
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 struct BoundingRectangle {
     min: Point,
@@ -22,7 +87,7 @@ struct BoundingRectangle {
 }
 
 impl BoundingRectangle {
-    pub fn new<I>(points: I) -> Result<BoundingRectangle, String>
+    fn new<I>(points: I) -> Result<BoundingRectangle, String>
     where
         I: Iterator<Item = Point>,
     {
@@ -51,246 +116,184 @@ impl BoundingRectangle {
     }
 }
 
-impl BoundingRectangle {
-    fn contains_point(&self, p: &Point) -> bool {
-        p.x >= self.min.x && p.x <= self.max.x && p.y >= self.min.y && p.y <= self.max.y
-    }
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut r = BufReader::new(io::stdin());
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+    let (sources, args) = parse_flags(&args)?;
+    match args {
+        [] => {
+            do_it(&mut r, &sources)?;
+        }
+        // works out where the sand ends up instead of dropping it
+        ["reachable"] => {
+            reachable(&mut r, &sources)?;
+        }
+        // times both ways on a random cave about this big
+        ["bench", size, seed] => {
+            bench(size.parse()?, seed.parse()?)?;
+        }
+        _ => Err(format!("unrecognized arguments: {:?}", args))?,
+    };
+    Ok(())
 }
 
-struct Grid<T> {
-    bounds: BoundingRectangle,
-    width: usize,
-    height: usize,
-    data: Vec<T>,
-}
+// the sources, and the rest of the arguments
+type Flags<'a, 'b> = (Vec<Point>, &'b [&'a str]);
 
-impl<T> Grid<T>
-where
-    T: Copy,
-{
-    pub fn new(bounds: &BoundingRectangle, initial_value: T) -> Self {
-        let mut result = Grid {
-            bounds: bounds.clone(),
-            width: (bounds.max.x - bounds.min.x + 1) as usize,
-            height: (bounds.max.y - bounds.min.y + 1) as usize,
-            data: Vec::new(),
+// any number of "--source x,y" before the command, or just 500,0 if there aren't any
+fn parse_flags<'a, 'b>(args: &'b [&'a str]) -> Result<Flags<'a, 'b>, Box<dyn Error>> {
+    let mut sources = Vec::new();
+    let mut args = args;
+    loop {
+        args = match args {
+            ["--source", value, rest @ ..] => {
+                sources.push(parse_point(value)?);
+                rest
+            }
+            _ => break,
         };
-        result
-            .data
-            .resize(result.width * result.height, initial_value);
-        result
     }
-
-    pub fn get(&self, p: Point) -> Result<T, Box<dyn Error>> {
-        if self.bounds.contains_point(&p) {
-            Ok(self.data[(p.x - self.bounds.min.x) as usize
-                + (p.y - self.bounds.min.y) as usize * self.width])
-        } else {
-            Err(format!(
-                "out of bounds: {:?}, bounds = {:?}",
-                p, self.bounds
-            ))?
-        }
+    if sources.is_empty() {
+        sources.push(Point { x: 500, y: 0 });
     }
+    Ok((sources, args))
+}
 
-    pub fn set(&mut self, p: Point, value: T) -> Result<(), Box<dyn Error>> {
-        if self.bounds.contains_point(&p) {
-            self.data[(p.x - self.bounds.min.x) as usize
-                + (p.y - self.bounds.min.y) as usize * self.width] = value;
-            Ok(())
-        } else {
-            Err(format!(
-                "out of bounds: {:?}, bounds = {:?}",
-                p, self.bounds
-            ))?
-        }
-    }
+fn do_it(r: &mut impl std::io::Read, sources: &[Point]) -> Result<usize, Box<dyn Error>> {
+    let shapes = parse_lines(r)?;
 
-    pub fn set_line(&mut self, line: &Line, value: T) -> Result<(), Box<dyn Error>> {
-        let mut p = line.a;
-        while p != line.b {
-            self.set(p, value)?;
-            if p.x < line.b.x {
-                p.x += 1;
-            } else if p.x > line.b.x {
-                p.x -= 1;
-            }
-            if p.y < line.b.y {
-                p.y += 1;
-            } else if p.y > line.b.y {
-                p.y -= 1;
-            }
-        }
-        self.set(p, value)?;
-        Ok(())
-    }
-}
+    // the floor is two below the lowest rock, and goes on forever either way
+    let mut cave = Cave::new(&shapes, Some(2), sources)?;
+    let total = cave.fill();
 
-impl Grid<char> {
-    pub fn draw(&self) -> Result<(), Box<dyn Error>> {
-        for y in self.bounds.min.y..self.bounds.max.y {
-            for x in self.bounds.min.x..self.bounds.max.x {
-                print!("{}", self.get(Point { x, y })?);
-            }
-            println!("");
-        }
-        Ok(())
-    }
-}
+    println!("final environment");
+    print!("{}", cave.draw());
+    println!();
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let mut r = BufReader::new(io::stdin());
-    do_it(&mut r)?;
-    Ok(())
+    println!("total sand dropped = {}", total);
+    Ok(total)
 }
 
-fn do_it(r: &mut impl std::io::Read) -> Result<usize, Box<dyn Error>> {
-    let lines = BufReader::new(r)
-        .lines()
-        .collect::<Result<Vec<_>, _>>()?
-        .iter()
-        .map(|line| parse_line(line))
-        .collect::<Result<Vec<_>, _>>()?;
-    let lines = lines
-        .iter()
-        .flatten()
-        .map(|line| line.clone())
-        .collect::<Vec<_>>();
-    for line in lines.iter() {
-        println!("line = {:?}", line);
-    }
+fn reachable(r: &mut impl std::io::Read, sources: &[Point]) -> Result<usize, Box<dyn Error>> {
+    let shapes = parse_lines(r)?;
+    let total = reachability::settled(&shapes, 2, sources)?;
+    println!("total sand settled = {}", total);
+    Ok(total)
+}
 
-    let bounds = {
-        let mut result =
-            BoundingRectangle::new(lines.iter().flat_map(|line| vec![line.a, line.b]))?;
-        result.min.y = 0;
-        result
+// bits of rock scattered around under 500,0, as wide as it is deep, and the same every time for the
+// same seed
+fn generate(size: i32, seed: u64) -> Vec<Shape> {
+    let mut state = seed;
+    let mut random = |n: i32| {
+        // a linear congruential generator, which is plenty random enough for rocks
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((state >> 33) % n.max(1) as u64) as i32
     };
-    println!("bounds = {:?}", bounds);
-
-    let lines = {
-        let mut result = lines;
-        let height = bounds.max.y - bounds.min.y;
-        result.push(Line {
-            a: Point {
-                x: bounds.min.x - height,
-                y: bounds.max.y + 2,
-            },
-            b: Point {
-                x: bounds.max.x + height,
-                y: bounds.max.y + 2,
-            },
+    let mut results = Vec::new();
+    for _ in 0..size / 4 + 1 {
+        let a = Point {
+            x: 500 - size / 2 + random(size),
+            y: 2 + random(size),
+        };
+        let length = random(size / 16 + 1);
+        let b = if random(2) == 0 {
+            Point {
+                x: a.x + length,
+                y: a.y,
+            }
+        } else {
+            Point {
+                x: a.x,
+                y: a.y + length,
+            }
+        };
+        results.push(Shape {
+            material: Material::Rock,
+            kind: ShapeKind::Line(Line { a, b }),
         });
-        println!("added new infinite line at y = {}", bounds.max.y);
-        result
-    };
-
-    let bounds = {
-        let mut result = bounds;
-        let height = bounds.max.y - bounds.min.y;
-        result.min.x -= height;
-        result.max.x += height;
-        result.max.y += 3;
-        result
-    };
-    println!("adjusted bounds = {:?}", bounds);
-
-    let mut grid = Grid::new(&bounds, '.');
-    for line in lines.iter() {
-        grid.set_line(&line, '#')?;
     }
+    results
+}
 
-    let mut sand_at_rest = Vec::new();
-    let mut sand_in_motion = None;
+fn bench(size: i32, seed: u64) -> Result<(), Box<dyn Error>> {
+    let shapes = generate(size, seed);
+    let sources = [Point { x: 500, y: 0 }];
 
-    let mut tick = 0;
-    loop {
-        // generate new sand
-        if sand_in_motion.is_none() {
-            sand_in_motion = Some(Point { x: 500, y: 0 });
-        }
+    let start = Instant::now();
+    let simulated = Cave::new(&shapes, Some(2), &sources)?.fill();
+    println!("simulated = {} in {:?}", simulated, start.elapsed());
 
-        // look at the locations we can move sand to and find the next location, or none if no new location is possible
-        let mut new_location = None;
-        for proposed_new_point in [
-            Point {
-                x: sand_in_motion.unwrap().x,
-                y: sand_in_motion.unwrap().y + 1,
-            },
-            Point {
-                x: sand_in_motion.unwrap().x - 1,
-                y: sand_in_motion.unwrap().y + 1,
-            },
-            Point {
-                x: sand_in_motion.unwrap().x + 1,
-                y: sand_in_motion.unwrap().y + 1,
-            },
-        ] {
-            // nothing at that location yet
-            if grid.get(proposed_new_point)? == '.' {
-                new_location = Some(proposed_new_point);
-                break;
-            }
-        }
-
-        // either we moved the sand, or we can't and it stops here
-        if let Some(new_location) = new_location {
-            // check against the bounds
-            if bounds.contains_point(&new_location) {
-                // still in bounds, we can move here
-                sand_in_motion = Some(new_location);
-            } else {
-                // out of bounds, error
-                Err("shouldn't reach this")?;
-            }
-        } else {
-            // no new location found, we're done with this one
-            sand_at_rest.push(sand_in_motion.unwrap());
-            grid.set(sand_in_motion.unwrap(), 'o')?;
-            if sand_in_motion.unwrap() == (Point { x: 500, y: 0 }) {
-                // we've filled up to the origin point so done
-                break;
-            }
-            sand_in_motion = None;
-        }
+    let start = Instant::now();
+    let reached = reachability::settled(&shapes, 2, &sources)?;
+    println!("reachable = {} in {:?}", reached, start.elapsed());
 
-        if tick % 1000 == 0 {
-            println!("tick = {}, total so far = {}", tick, sand_at_rest.len());
-            grid.draw()?;
-            println!("");
-        }
-        tick += 1;
+    if simulated != reached {
+        Err(format!("they don't agree: {} and {}", simulated, reached))?
     }
+    Ok(())
+}
 
-    println!("final environment");
-    println!("tick = {}", tick);
-    grid.draw()?;
-    println!("");
-
-    println!("total sand dropped = {}", sand_at_rest.len());
-    Ok(sand_at_rest.len())
+fn parse_lines(r: &mut impl std::io::Read) -> Result<Vec<Shape>, Box<dyn Error>> {
+    let mut shapes = Vec::new();
+    for (i, line) in BufReader::new(r).lines().enumerate() {
+        let parsed = parse_line(&line?).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        shapes.extend(parsed);
+    }
+    for shape in shapes.iter() {
+        println!("shape = {:?}", shape);
+    }
+    Ok(shapes)
 }
 
-fn parse_line(s: &str) -> Result<Vec<Line>, Box<dyn Error>> {
+// "[material:] [rect] x,y -> x,y ...", where the material is rock unless it says otherwise, a
+// rectangle has just its two corners, and anything after a # is ignored
+fn parse_line(s: &str) -> Result<Vec<Shape>, Box<dyn Error>> {
+    let s = s.split('#').next().unwrap().trim();
+    if s.is_empty() {
+        return Ok(Vec::new());
+    }
+    let (material, s) = match s.split_once(':') {
+        Some((material, rest)) => (material.parse()?, rest.trim()),
+        None => (Material::Rock, s),
+    };
+    let (rectangle, s) = match s.strip_prefix("rect ") {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
     let points = s
         .split("->")
         .map(parse_point)
         .collect::<Result<Vec<_>, _>>()?;
+    if rectangle {
+        if points.len() != 2 {
+            Err(format!("expected a rectangle's two corners, got {}", s))?
+        }
+        return Ok(vec![Shape {
+            material,
+            kind: ShapeKind::Rectangle(points[0], points[1]),
+        }]);
+    }
     if points.len() < 2 {
         Err(format!("expected at least two points, got {}", s))?
     }
-    let mut results = Vec::new();
-    for i in 0..(points.len() - 1) {
-        results.push(Line {
-            a: points[i],
-            b: points[i + 1],
-        });
-    }
-    Ok(results)
+    Ok(points
+        .windows(2)
+        .map(|pair| Shape {
+            material,
+            kind: ShapeKind::Line(Line {
+                a: pair[0],
+                b: pair[1],
+            }),
+        })
+        .collect())
 }
 
 fn parse_point(s: &str) -> Result<Point, Box<dyn Error>> {
-    let parts = s.trim().split(",").collect::<Vec<_>>();
+    let parts = s.trim().split(',').collect::<Vec<_>>();
     if parts.len() == 2 {
         Ok(Point {
             x: parts[0].parse()?,
@@ -318,8 +321,108 @@ mod tests {
                 &mut r"498,4 -> 498,6 -> 496,6
 503,4 -> 502,4 -> 502,9 -> 494,9"
                     .as_bytes(),
+                &[Point { x: 500, y: 0 }],
             )?
         );
         Ok(())
     }
+
+    #[test]
+    fn reachable_agrees() -> Result<(), Box<dyn Error>> {
+        let sample = "498,4 -> 498,6 -> 496,6\n503,4 -> 502,4 -> 502,9 -> 494,9";
+        for sources in [
+            vec![Point { x: 500, y: 0 }],
+            vec![Point { x: 490, y: 3 }, Point { x: 505, y: -4 }],
+            // inside rock, so it's covered from the start
+            vec![Point { x: 498, y: 5 }],
+        ] {
+            assert_eq!(
+                do_it(&mut sample.as_bytes(), &sources)?,
+                reachable(&mut sample.as_bytes(), &sources)?
+            );
+        }
+        assert_eq!(
+            do_it(
+                &mut std::fs::File::open("input")?,
+                &[Point { x: 500, y: 0 }]
+            )?,
+            reachable(
+                &mut std::fs::File::open("input")?,
+                &[Point { x: 500, y: 0 }]
+            )?
+        );
+        for seed in 0..10 {
+            bench(40, seed)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn bresenham() {
+        let points = |a: (i32, i32), b: (i32, i32)| {
+            Line {
+                a: Point { x: a.0, y: a.1 },
+                b: Point { x: b.0, y: b.1 },
+            }
+            .points()
+            .iter()
+            .map(|p| (p.x, p.y))
+            .collect::<Vec<_>>()
+        };
+        // the same cases as day 8's viewshed lines, which share this formulation
+        assert_eq!(
+            vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)],
+            points((0, 0), (4, 2))
+        );
+        assert_eq!(vec![(2, 3), (1, 2), (1, 1), (0, 0)], points((2, 3), (0, 0)));
+        // steep
+        assert_eq!(
+            vec![(0, 0), (0, 1), (1, 2), (1, 3), (1, 4)],
+            points((0, 0), (1, 4))
+        );
+        assert_eq!(
+            vec![(1, 4), (1, 3), (0, 2), (0, 1), (0, 0)],
+            points((1, 4), (0, 0))
+        );
+        // negative slopes
+        assert_eq!(
+            vec![(3, 0), (2, -1), (1, -1), (0, -2)],
+            points((3, 0), (0, -2))
+        );
+        assert_eq!(
+            vec![(0, -2), (1, -1), (2, -1), (3, 0)],
+            points((0, -2), (3, 0))
+        );
+        assert_eq!(vec![(0, 0), (1, 1), (2, 1), (3, 2)], points((0, 0), (3, 2)));
+        assert_eq!(vec![(2, 3), (2, 2), (1, 1), (1, 0)], points((2, 3), (1, 0)));
+        assert_eq!(vec![(5, 5), (4, 4), (3, 3)], points((5, 5), (3, 3)));
+    }
+
+    #[test]
+    fn scan_format() -> Result<(), Box<dyn Error>> {
+        let shapes = parse_lines(
+            &mut r"# a comment, and then a blank line
+
+498,4 -> 500,6 # a diagonal
+water left: 490,0 -> 491,0
+platform: rect 490,2 -> 491,3"
+                .as_bytes(),
+        )?;
+        assert_eq!(3, shapes.len());
+        assert_eq!(3, shapes[0].points().len());
+        assert_eq!(Material::Water(-1), shapes[1].material);
+        assert_eq!(Material::Platform, shapes[2].material);
+        assert_eq!(4, shapes[2].points().len());
+
+        let error = |s: &str| parse_lines(&mut s.as_bytes()).err().unwrap().to_string();
+        assert_eq!(
+            "line 3: expected a rectangle's two corners, got 1,1",
+            error("0,0 -> 1,1\n\nrect 1,1")
+        );
+        assert_eq!(
+            "line 2: unknown material: \"lava\"",
+            error("# fine\nlava: 0,0 -> 1,1")
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,74 @@
+use std::collections::HashSet;
+
+use crate::{sand::Material, Point, Shape};
+
+// how much sand settles on a floor this far below the lowest rock, without dropping any of it
+//
+// sand keeps piling up until every source is covered, and by then it's filled every cell a grain
+// could have got to, which is any that isn't rock and has sand in one of the three above it, so
+// it only takes one pass down the rows, as long as there's nothing but rock for sand to go through
+// or get carried along
+pub fn settled(shapes: &[Shape], floor: i32, sources: &[Point]) -> Result<usize, String> {
+    if let Some(shape) = shapes.iter().find(|s| s.material != Material::Rock) {
+        Err(format!("only works with rock, not {:?}", shape.material))?
+    }
+    let rock = shapes
+        .iter()
+        .flat_map(|shape| shape.points())
+        .collect::<HashSet<_>>();
+    let bottom = rock
+        .iter()
+        .map(|p| p.y)
+        .max()
+        .ok_or("expected at least one rock")?;
+    let floor = bottom + floor;
+    let top = sources
+        .iter()
+        .map(|p| p.y)
+        .min()
+        .ok_or("expected at least one source of sand")?;
+
+    // nothing can spread further sideways than it falls, so only two rows that wide are needed
+    let spread = (floor - top).max(0);
+    let left = sources.iter().map(|p| p.x).min().unwrap() - spread - 1;
+    let right = sources.iter().map(|p| p.x).max().unwrap() + spread + 1;
+    let width = (right - left + 1) as usize;
+
+    let mut above = vec![false; width];
+    let mut row = vec![false; width];
+    // the first and last columns with sand in the row above, if there's any, so only the ones
+    // between them and any sources in this row need looking at
+    let mut span: Option<(usize, usize)> = None;
+    let mut total = 0;
+    for y in top..floor {
+        let mut columns = span.map(|(first, last)| (first - 1, last + 1));
+        for source in sources.iter().filter(|p| p.y == y) {
+            let i = (source.x - left) as usize;
+            columns = Some(columns.map_or((i, i), |(first, last)| (first.min(i), last.max(i))));
+        }
+        let mut next_span = None;
+        if let Some((first, last)) = columns {
+            for i in first..=last {
+                let p = Point {
+                    x: left + i as i32,
+                    y,
+                };
+                let reached = above[i - 1] || above[i] || above[i + 1] || sources.contains(&p);
+                if reached && !rock.contains(&p) {
+                    row[i] = true;
+                    total += 1;
+                    next_span = Some(next_span.map_or((i, i), |(first, last): (usize, usize)| {
+                        (first.min(i), last.max(i))
+                    }));
+                }
+            }
+        }
+        // the row above is done with, so it can be cleared and used for the next one
+        if let Some((first, last)) = span {
+            above[first..=last].fill(false);
+        }
+        std::mem::swap(&mut above, &mut row);
+        span = next_span;
+    }
+    Ok(total)
+}